@@ -0,0 +1,49 @@
+use std::convert::TryFrom;
+
+/// Cursor over the unread part of a byte slice, for decoding the crate's binary formats.  Every
+/// read returns `None`, consuming nothing, if too few bytes remain.
+#[derive(Debug)]
+pub(crate) struct ByteReader<'a>(&'a [u8]);
+
+impl<'a> ByteReader<'a> {
+    pub(crate) const fn new(bytes: &'a [u8]) -> Self {
+        Self(bytes)
+    }
+
+    /// Return the number of unread bytes
+    pub(crate) const fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub(crate) const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) const fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        match n <= self.0.len() {
+            true => {
+                let (taken, rest) = self.0.split_at(n);
+                self.0 = rest;
+                Some(taken)
+            }
+            false => None,
+        }
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        self.take(1).and_then(|bytes| bytes.first().copied())
+    }
+
+    /// Reads a little-endian `u64`
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        self.take(8)
+            .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+            .map(u64::from_le_bytes)
+    }
+
+    /// Reads a byte string prefixed with its length as a little-endian `u64`
+    pub(crate) fn len_prefixed(&mut self) -> Option<&'a [u8]> {
+        let len = usize::try_from(self.u64()?).ok()?;
+        self.take(len)
+    }
+}
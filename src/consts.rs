@@ -1,5 +1,31 @@
 pub mod msg;
 
+/// Largest batch `MaxFeeTxHandler` searches exhaustively when its `SearchMode` is `Auto`
+pub const MAX_FEE_EXACT_SEARCH_MAX_TXS: usize = 20;
+/// Passes over the batch `MaxFeeTxHandler` makes when its `SearchMode` is `Auto` and the batch is
+/// too large to search exhaustively
+pub const MAX_FEE_HEURISTIC_MAX_ROUNDS: usize = 16;
+
+/// Log records a `FileUtxoStore` accumulates before folding them into its snapshot
+pub const FILE_UTXO_STORE_COMPACTION_INTERVAL: usize = 1024;
+pub const FILE_UTXO_STORE_LOG_FILE_NAME: &str = "utxo.log";
+pub const FILE_UTXO_STORE_SNAPSHOT_FILE_NAME: &str = "utxo.snapshot";
+pub const FILE_UTXO_STORE_SNAPSHOT_TMP_FILE_NAME: &str = "utxo.snapshot.tmp";
+
+/// Domain separator hashed into each element of a `UtxoPool` commitment
+pub const UTXO_SET_COMMITMENT_DOMAIN: &str = "scrooge_coin/utxo-set-commitment/v1";
+
+/// Prefix of every canonically encoded `Tx`
+pub const TX_ENCODING_MAGIC: [u8; 4] = *b"SCRG";
+/// Version of the canonical `Tx` encoding produced by `Tx::encode()`
+pub const TX_ENCODING_VERSION: u8 = 1;
+
+/// Length in bytes of a `TxHash`
+pub const TX_HASH_LENGTH: usize = 32;
+
+/// Longest context RFC 8032 permits for an Ed25519ph signature
+pub const ED25519PH_MAX_CONTEXT_LEN: usize = 255;
+
 pub const ED25519_DALEK_SIGNATURE_TYPE_NAME: &str = "Signature(ed25519_dalek::Signature)";
 pub const ED25519_DALEK_KEY_PAIR_TYPE_NAME: &str = "Signature(ed25519_dalek::KeyPair)";
 pub const ED25519_DALEK_PUBLIC_KEY_TYPE_NAME: &str = "PublicKey(ed25519_dalek::PublicKey)";
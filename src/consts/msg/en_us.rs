@@ -10,6 +10,70 @@ Transaction to build a Transaction";
 pub const ERR_INTERNAL_SERIALIZATION: &str = "Internal error: Conversion of in-memory data \
 structure to raw bytes failed";
 pub const ERR_TX_IDX_OUT_OF_BOUNDS: &str = "Error: Supplied Transaction Index is out of bounds";
+pub const ERR_TX_IDX_EXCEEDS_USIZE: &str = "Error: Transaction Index does not fit in a `usize` \
+on this platform";
+pub const ERR_POSITION_EXCEEDS_TX_IDX: &str = "Error: Position does not fit in a Transaction Index";
 pub const ERR_INTERNAL: &str = "Internal error (bug)";
 pub const ERR_KEY_CONSTRUCTION: &str = "Error constructing key from byte slice";
 pub const ERR_SIGNATURE: &str = "Error constructing signature";
+pub const ERR_ED25519PH_CONTEXT_TOO_LONG: &str = "Error: Ed25519ph context is longer than 255 \
+    bytes";
+pub const ERR_TX_MISSING_UTXO: &str = "Error: Transaction claims a `Utxo` which is not in the \
+`UtxoPool`";
+pub const ERR_TX_BAD_SIGNATURE: &str = "Error: Transaction input signature failed verification";
+pub const ERR_TX_MISSING_SIGNATURE: &str = "Error: Transaction input is not signed";
+pub const ERR_SIGHASH_SINGLE_WITHOUT_OUTPUT: &str = "Error: Transaction input is signed with \
+    `SigHash` `Single` but there is no output at its index";
+pub const ERR_TX_UNEXPECTED_GENESIS_INPUT: &str = "Error: Transaction contains a Genesis input";
+pub const ERR_TX_DUPLICATE_CLAIM: &str = "Error: Transaction claims the same `Utxo` more than once";
+pub const ERR_TX_NEGATIVE_OUTPUT: &str = "Error: Transaction output value is negative";
+pub const ERR_TX_INSUFFICIENT_INPUTS: &str = "Error: Sum of transaction input values is less than \
+the sum of its output values";
+pub const ERR_TX_VALUE_OVERFLOW: &str = "Error: Sum of transaction values overflowed";
+pub const ERR_STALE_SIMULATION: &str = "Error: The `UtxoPool` has changed since the epoch was \
+simulated";
+pub const ERR_INVALID_CHECKPOINT: &str = "Error: The `UtxoPool` checkpoint has been committed or \
+rolled back past";
+pub const ERR_DELTA_REMOVES_MISSING_UTXO: &str = "Error: `UtxoDelta` removes a `Utxo` which is \
+not in the `UtxoPool` with the recorded tx output";
+pub const ERR_DELTA_ADDS_EXISTING_UTXO: &str = "Error: `UtxoDelta` adds a `Utxo` which is already \
+in the `UtxoPool`";
+pub const ERR_SUPPLY_OVERFLOW: &str =
+    "Error: Adding the `Utxo` would overflow the total supply of \
+the `UtxoPool`";
+pub const ERR_PARTIALLY_SIGNED_TX_MISMATCH: &str = "Error: Partially signed transactions being \
+    combined are for different transactions";
+pub const ERR_PARTIALLY_SIGNED_TX_INCOMPLETE: &str = "Error: Partially signed transaction has \
+    inputs without a valid signature";
+pub const ERR_MALFORMED_PARTIALLY_SIGNED_TX: &str = "Error: Partially signed transaction does not \
+    hold one spent output and signature slot per unsigned input";
+pub const ERR_UTXO_STORE_IO: &str = "Error: I/O on the `UtxoStore` failed";
+pub const ERR_CORRUPT_UTXO_STORE: &str = "Error: A `UtxoStore` file is corrupt";
+pub const ERR_UTXO_STORE_POISONED: &str =
+    "Error: The `UtxoStore` log may end in a partial record; \
+compact or reopen the store";
+pub const ERR_MALFORMED_UTXO_SET_COMMITMENT: &str = "Error: Bytes do not encode a `UtxoPool` \
+commitment";
+pub const ERR_UTXO_MERKLE_ROOT_LENGTH: &str = "Error: A `UtxoMerkleRoot` must be 32 bytes long";
+pub const ERR_MALFORMED_UTXO_PROOF: &str = "Error: Bytes do not encode a `UtxoProof`";
+pub const ERR_KEY_PAIR_MISMATCH: &str = "Error: Key pair's public key does not belong to its \
+secret key";
+pub const ERR_TX_HASH_MISMATCH: &str = "Error: Transaction's recorded hash does not match its \
+contents";
+pub const ERR_MALFORMED_TX_ENCODING: &str = "Error: Malformed transaction encoding";
+pub const ERR_UNSUPPORTED_TX_ENCODING_VERSION: &str = "Error: Unsupported transaction encoding \
+version";
+pub const ERR_NON_CANONICAL_TX_ENCODING: &str = "Error: Transaction encoding is not canonical";
+pub const ERR_TX_ENCODING_BAD_MAGIC: &str = "missing magic prefix";
+pub const ERR_TX_ENCODING_TRUNCATED: &str = "truncated";
+pub const ERR_TX_ENCODING_TRAILING_BYTES: &str = "trailing bytes after transaction";
+pub const ERR_TX_ENCODING_BAD_TAG: &str = "unknown input tag";
+pub const ERR_TX_ENCODING_BAD_VALUE: &str = "invalid output value";
+pub const ERR_TX_ENCODING_BAD_ADDRESS: &str = "invalid output address";
+pub const ERR_TX_ENCODING_BAD_SIGNATURE: &str = "invalid input signature";
+pub const ERR_TX_ENCODING_BAD_SIGHASH: &str = "invalid input sighash";
+pub const ERR_TX_ENCODING_BAD_TX_HASH: &str = "invalid input tx hash";
+pub const ERR_TX_HASH_LENGTH: &str = "Error: A `TxHash` must be 32 bytes long";
+pub const ERR_PARSE_TX_HASH: &str = "Error: A `TxHash` must be written as 64 hex digits";
+pub const ERR_PARSE_UTXO: &str = "Error: A `Utxo` must be written as `txhash:idx`, a 64 hex digit \
+`TxHash` and a decimal `TxIdx`";
@@ -1,12 +1,24 @@
-use crate::tx::TxIdx;
+use crate::tx::{OutputTx, TxHash, TxIdx};
 use crate::{consts::msg, Utxo};
 use ed25519_dalek::SignatureError;
+use rust_decimal::Decimal;
+use std::{io, path::PathBuf};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("{}: {:?}", msg::ERR_ADD_DUPLICATE_UTXO_ATTEMPTED, 0)]
-    AddDuplicateUtxoAttempted(Utxo),
+    #[error(
+        "{}: {:?} (existing: {:?}, attempted: {:?})",
+        msg::ERR_ADD_DUPLICATE_UTXO_ATTEMPTED,
+        .utxo,
+        .existing,
+        .attempted
+    )]
+    AddDuplicateUtxoAttempted {
+        utxo: Utxo,
+        existing: OutputTx,
+        attempted: OutputTx,
+    },
     #[error("{}.", msg::ERR_CANNOT_SIGN_GENESIS)]
     CannotSignGenesisBlock,
     #[error("{}.", msg::ERR_CANNOT_UNSIGN_GENESIS)]
@@ -17,8 +29,72 @@ pub enum Error {
     CannotBuildTxWithoutOutputTxs,
     #[error("{}.", msg::ERR_TX_IDX_OUT_OF_BOUNDS)]
     TxnIdxOutOfBounds(TxIdx),
+    #[error("{}: {:?}", msg::ERR_TX_IDX_EXCEEDS_USIZE, .0)]
+    TxIdxExceedsUsize(TxIdx),
+    #[error("{}: {}", msg::ERR_POSITION_EXCEEDS_TX_IDX, .0)]
+    PositionExceedsTxIdx(usize),
+    #[error("{}: input {:?}", msg::ERR_SIGHASH_SINGLE_WITHOUT_OUTPUT, .0)]
+    SigHashSingleWithoutOutput(TxIdx),
     #[error("{}: {:?}", msg::ERR_KEY_CONSTRUCTION, 0)]
     KeyConstruction(SignatureError),
+    #[error("{}: {:?}", msg::ERR_SIGNATURE, 0)]
+    SignatureConstruction(SignatureError),
+    #[error("{}: {} bytes", msg::ERR_ED25519PH_CONTEXT_TOO_LONG, .0)]
+    Ed25519phContextTooLong(usize),
+    #[error(
+        "{}: simulated against version {}, pool is at version {}",
+        msg::ERR_STALE_SIMULATION,
+        .simulated,
+        .current
+    )]
+    StaleSimulation { simulated: u64, current: u64 },
+    #[error("{}.", msg::ERR_INVALID_CHECKPOINT)]
+    InvalidCheckpoint,
+    #[error("{}: {:?}", msg::ERR_TX_MISSING_UTXO, .0)]
+    MissingUtxo(Utxo),
+    #[error("{}.", msg::ERR_PARTIALLY_SIGNED_TX_MISMATCH)]
+    PartiallySignedTxMismatch,
+    #[error("{}: inputs {:?}", msg::ERR_PARTIALLY_SIGNED_TX_INCOMPLETE, .0)]
+    PartiallySignedTxIncomplete(Vec<TxIdx>),
+    #[error("{}.", msg::ERR_MALFORMED_PARTIALLY_SIGNED_TX)]
+    MalformedPartiallySignedTx,
+    #[error("{}: {:?}", msg::ERR_DELTA_REMOVES_MISSING_UTXO, .0)]
+    DeltaRemovesMissingUtxo(Utxo),
+    #[error("{}: {:?}", msg::ERR_DELTA_ADDS_EXISTING_UTXO, .0)]
+    DeltaAddsExistingUtxo(Utxo),
+    #[error("{}: {:?}", msg::ERR_SUPPLY_OVERFLOW, .0)]
+    SupplyOverflow(Utxo),
+    #[error(
+        "{}: recorded {:?}, computed {:?}",
+        msg::ERR_TX_HASH_MISMATCH,
+        .recorded,
+        .computed
+    )]
+    TxHashMismatch { recorded: TxHash, computed: TxHash },
+    #[error("{}: got {} bytes", msg::ERR_TX_HASH_LENGTH, .0)]
+    TxHashLength(usize),
+    #[error("{}: {:?}", msg::ERR_PARSE_TX_HASH, .0)]
+    ParseTxHash(String),
+    #[error("{}: {:?}", msg::ERR_PARSE_UTXO, .0)]
+    ParseUtxo(String),
+    #[error("{}: {}", msg::ERR_MALFORMED_TX_ENCODING, .0)]
+    MalformedTxEncoding(&'static str),
+    #[error("{}: {}", msg::ERR_UNSUPPORTED_TX_ENCODING_VERSION, .0)]
+    UnsupportedTxEncodingVersion(u8),
+    #[error("{}.", msg::ERR_NON_CANONICAL_TX_ENCODING)]
+    NonCanonicalTxEncoding,
+    #[error("{}: {}", msg::ERR_UTXO_STORE_IO, 0)]
+    UtxoStoreIo(io::Error),
+    #[error("{}: {:?}", msg::ERR_CORRUPT_UTXO_STORE, .0)]
+    CorruptUtxoStore(PathBuf),
+    #[error("{}: {:?}", msg::ERR_UTXO_STORE_POISONED, .0)]
+    UtxoStorePoisoned(PathBuf),
+    #[error("{}.", msg::ERR_MALFORMED_UTXO_SET_COMMITMENT)]
+    MalformedUtxoSetCommitment,
+    #[error("{}: got {} bytes", msg::ERR_UTXO_MERKLE_ROOT_LENGTH, .0)]
+    UtxoMerkleRootLength(usize),
+    #[error("{}.", msg::ERR_MALFORMED_UTXO_PROOF)]
+    MalformedUtxoProof,
 }
 
 impl From<SignatureError> for Error {
@@ -27,9 +103,39 @@ impl From<SignatureError> for Error {
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::UtxoStoreIo(err)
+    }
+}
+
+/// Reasons a tx can fail validation against a `UtxoPool`
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum TxValidationError {
+    #[error("{}: {:?}", msg::ERR_TX_MISSING_UTXO, .0)]
+    MissingUtxo(Utxo),
+    #[error("{}: input {:?}", msg::ERR_TX_BAD_SIGNATURE, .input)]
+    BadSignature { input: TxIdx },
+    #[error("{}: input {:?}", msg::ERR_TX_MISSING_SIGNATURE, .input)]
+    MissingSignature { input: TxIdx },
+    #[error("{}: input {:?}", msg::ERR_SIGHASH_SINGLE_WITHOUT_OUTPUT, .input)]
+    SigHashSingleWithoutOutput { input: TxIdx },
+    #[error("{}: input {:?}", msg::ERR_TX_UNEXPECTED_GENESIS_INPUT, .input)]
+    UnexpectedGenesisInput { input: TxIdx },
+    #[error("{}: {:?}", msg::ERR_TX_DUPLICATE_CLAIM, .0)]
+    DuplicateClaim(Utxo),
+    #[error("{}: output {:?}", msg::ERR_TX_NEGATIVE_OUTPUT, .0)]
+    NegativeOutput(TxIdx),
+    #[error("{}: {} < {}", msg::ERR_TX_INSUFFICIENT_INPUTS, .in_sum, .out_sum)]
+    InsufficientInputs { in_sum: Decimal, out_sum: Decimal },
+    #[error("{}.", msg::ERR_TX_VALUE_OVERFLOW)]
+    ValueOverflow,
+}
+
 #[derive(Debug, Error)]
 pub enum PanicError {
     #[error("{}: {:?}", msg::ERR_KEY_CONSTRUCTION, 0)]
+    #[allow(dead_code)]
     DeserializeKey(SignatureError),
     #[error("{}: {}", msg::ERR_INTERNAL_SERIALIZATION, 0)]
     Serialization(bincode::Error),
@@ -1,34 +1,78 @@
 use crate::tx::Address;
 use crate::{
-    consts,
+    consts::{self, msg},
     tx::{PublicKey, SecretKey},
+    Error, Result, Signature,
 };
+use ed25519_dalek::Signer;
 use rand::{CryptoRng, Rng};
-use serde::{Serialize, Serializer};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use sha2::Sha512;
-use std::hash::{Hash, Hasher};
+use std::{
+    fmt::{Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+};
 
+/// Ed25519 key pair.  Signing follows RFC 8032, so signatures interoperate with other Ed25519
+/// implementations.
 pub struct KeyPair(ed25519_dalek::Keypair);
 
 impl KeyPair {
     pub fn new<R: CryptoRng + Rng>(csprng: &mut R) -> Self {
-        Self(ed25519_dalek::Keypair::generate::<Sha512, R>(csprng))
+        Self(ed25519_dalek::Keypair::generate(csprng))
+    }
+
+    /// Return the key pair of `secret_key`, deriving its public key as RFC 8032 specifies
+    #[must_use]
+    pub fn from_secret_key(secret_key: &SecretKey) -> Self {
+        let secret = ed25519_dalek::SecretKey::from_bytes(secret_key.as_bytes())
+            .unwrap_or_else(|err| unreachable!("{}", err));
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        Self(ed25519_dalek::Keypair { secret, public })
     }
 
     #[must_use]
     pub fn public_key(&self) -> PublicKey {
-        PublicKey::from_slice(self.0.public.as_bytes()).unwrap_or_else(|err| unreachable!(err))
+        PublicKey::from_slice(self.0.public.as_bytes())
+            .unwrap_or_else(|err| unreachable!("{}", err))
     }
 
     #[must_use]
     pub fn secret_key(&self) -> SecretKey {
-        SecretKey::from_slice(self.0.secret.as_bytes()).unwrap_or_else(|err| unreachable!(err))
+        SecretKey::from_slice(self.0.secret.as_bytes())
+            .unwrap_or_else(|err| unreachable!("{}", err))
+    }
+
+    /// Signs `message` with this key pair's secret key using Ed25519 (RFC 8032, section 5.1.6)
+    #[must_use]
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature(self.0.sign(message))
+    }
+
+    /// Signs the message fed into `prehashed_message` using Ed25519ph (RFC 8032, section 5.1),
+    /// under the optional `context` of at most 255 bytes.  The message is never held in full, so
+    /// large payloads can be streamed into the hasher.
+    pub fn sign_prehashed(
+        &self,
+        prehashed_message: Sha512,
+        context: Option<&[u8]>,
+    ) -> Result<Signature> {
+        let context_len = context.map_or(0, <[u8]>::len);
+        if context_len > consts::ED25519PH_MAX_CONTEXT_LEN {
+            Err(Error::Ed25519phContextTooLong(context_len))?;
+        }
+        Ok(Signature(
+            self.0.sign_prehashed(prehashed_message, context)?,
+        ))
     }
 }
 
 impl Hash for KeyPair {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        Hash::hash_slice(&self.0.to_bytes(), state)
+        Hash::hash_slice(&self.0.to_bytes(), state);
     }
 }
 
@@ -43,3 +87,36 @@ impl Serialize for KeyPair {
         )
     }
 }
+
+impl<'de> Deserialize<'de> for KeyPair {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as Deserializer<'de>>::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_newtype_struct(consts::ED25519_DALEK_KEY_PAIR_TYPE_NAME, KeyPairVisitor)
+    }
+}
+
+/// Accepts only the bytes of a valid ed25519 key pair (secret key followed by its public key)
+struct KeyPairVisitor;
+
+impl<'de> Visitor<'de> for KeyPairVisitor {
+    type Value = KeyPair;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(consts::ED25519_DALEK_KEY_PAIR_TYPE_NAME)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        let key_pair = ed25519_dalek::Keypair::from_bytes(&bytes).map_err(de::Error::custom)?;
+        match ed25519_dalek::PublicKey::from(&key_pair.secret) == key_pair.public {
+            true => Ok(KeyPair(key_pair)),
+            false => Err(de::Error::custom(msg::ERR_KEY_PAIR_MISMATCH)),
+        }
+    }
+}
@@ -1,14 +1,12 @@
 // To use the `unsafe` keyword, change to `#![allow(unsafe_code)]` (do not remove); aids auditing.
 #![forbid(unsafe_code)]
-#![forbid(bare_trait_objects)]
 // Safety-critical application lints
 #![deny(
     clippy::pedantic,
     clippy::float_cmp_const,
     clippy::indexing_slicing,
-    clippy::integer_arithmetic,
-    clippy::option_unwrap_used,
-    clippy::result_unwrap_used
+    clippy::arithmetic_side_effects,
+    clippy::unwrap_used
 )]
 #![warn(
     clippy::all,
@@ -17,11 +15,17 @@
     rust_2018_idioms,
     clippy::unused_self
 )]
+#![forbid(bare_trait_objects)]
 #![allow(
     clippy::iter_nth_zero,
+    clippy::large_enum_variant,
     clippy::match_bool,
     clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
     clippy::module_name_repetitions,
+    clippy::redundant_pub_crate,
+    clippy::result_large_err,
+    clippy::single_match_else,
     clippy::try_err
 )]
 // Uncomment before ship to reconcile use of possibly redundant crates, debug remnants, missing
@@ -29,20 +33,40 @@
 //#![warn(clippy::cargo, clippy::restriction, missing_docs, clippy::missing_errors_doc, warnings)]
 //#![deny(warnings)]
 
+mod byte_reader;
 mod consts;
 mod error;
 mod key_pair;
+mod max_fee_tx_handler;
 mod signature;
+#[cfg(test)]
+mod tests;
 mod tx;
 mod tx_handler;
 mod utxo;
+mod utxo_delta;
 mod utxo_pool;
+mod utxo_store;
 pub use {
-    error::Error,
+    error::{Error, TxValidationError},
     key_pair::KeyPair,
+    max_fee_tx_handler::{MaxFeeTxHandler, SearchMode},
     signature::Signature,
-    tx::{InputTx, Operation, OutputTx, PublicKey, SecretKey, Tx, TxHash, TxIdx},
-    tx_handler::TxHandler,
+    tx::{
+        FullySigned, InputTx, Modifiable, Operation, OutputTx, PartiallySigned, PartiallySignedTx,
+        PublicKey, SecretKey, SigHash, SigHashOutputs, SigningState, Tx, TxBuilder, TxHash, TxIdx,
+        Unsigned,
+    },
+    tx_handler::{
+        Conflict, ConflictPolicy, EpochReport, EpochSimulation, FirstSeen, HighestFee,
+        HighestFeeRate, LowestTxHash, ProposedTx, RejectedTx, RejectionReason, TxHandler,
+    },
     utxo::Utxo,
+    utxo_delta::UtxoDelta,
+    utxo_pool::{
+        verify_proof, Checkpoint, OccupiedUtxoEntry, UtxoEntry, UtxoMerkleRoot, UtxoPool,
+        UtxoProof, UtxoSetCommitment, VacantUtxoEntry,
+    },
+    utxo_store::{FileUtxoStore, UtxoStore},
 };
 pub type Result<T, E = Error> = std::result::Result<T, E>;
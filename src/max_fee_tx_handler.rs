@@ -0,0 +1,341 @@
+use crate::{consts, tx_handler, utxo_pool::UtxoPoolOverlay, Tx, TxHash, Utxo, UtxoPool};
+use rust_decimal::Decimal;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Strategy used by `MaxFeeTxHandler` to search for the highest-fee set of txs
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SearchMode {
+    /// `Exact` for batches of up to `MAX_FEE_EXACT_SEARCH_MAX_TXS` txs, `Heuristic` otherwise
+    #[default]
+    Auto,
+    /// Branch-and-bound search which always finds the highest-fee set; exponential in the batch
+    /// size
+    Exact,
+    /// Greedy highest-fee-first selection making at most `max_rounds` passes over the batch
+    Heuristic { max_rounds: usize },
+}
+
+#[derive(Debug)]
+pub struct MaxFeeTxHandler {
+    /// The handler's private copy of the current collection of UTXOs
+    utxo_pool: UtxoPool,
+    search_mode: SearchMode,
+}
+
+/// A statically valid tx under consideration by the exact search
+#[derive(Debug)]
+struct Candidate<'a> {
+    tx: &'a Tx,
+    fee: Decimal,
+    claims: Vec<Utxo>,
+    /// For each tx whose outputs `tx` spends, the positions (in the candidate list) of the
+    /// candidates with that tx's hash.  These differ only in their signatures, so including any one
+    /// of them satisfies the dependency.
+    parents: Vec<Vec<usize>>,
+}
+
+impl MaxFeeTxHandler {
+    /// Creates a public ledger whose current `UtxoPool` is a copy of `utxo_pool`
+    #[must_use]
+    pub fn new(utxo_pool: &UtxoPool) -> Self {
+        Self {
+            utxo_pool: utxo_pool.clone(),
+            search_mode: SearchMode::default(),
+        }
+    }
+
+    pub const fn set_search_mode(&mut self, search_mode: SearchMode) -> &mut Self {
+        self.search_mode = search_mode;
+        self
+    }
+
+    #[must_use]
+    pub const fn search_mode(&self) -> SearchMode {
+        self.search_mode
+    }
+
+    /// Return the handler's current `UtxoPool`
+    #[must_use]
+    pub const fn utxo_pool(&self) -> &UtxoPool {
+        &self.utxo_pool
+    }
+
+    /// Handles each epoch by receiving an unordered array of proposed transactions, returning the
+    /// mutually valid subset whose total fee (sum of input values less sum of output values) is
+    /// highest, and updating the current UTXO pool as appropriate.
+    pub fn handle_txs(&mut self, possible_txs: &[Tx]) -> Vec<Tx> {
+        let mut candidates = possible_txs.iter().collect::<Vec<_>>();
+        candidates.sort_by(|lhs, rhs| {
+            (lhs.hash(), lhs.witness_hash()).cmp(&(rhs.hash(), rhs.witness_hash()))
+        });
+        candidates.dedup_by(|lhs, rhs| lhs.witness_hash() == rhs.witness_hash());
+
+        let selected = match self.search_mode {
+            SearchMode::Auto if candidates.len() <= consts::MAX_FEE_EXACT_SEARCH_MAX_TXS => {
+                self.exact_search(&candidates)
+            }
+            SearchMode::Auto => {
+                self.heuristic_search(&candidates, consts::MAX_FEE_HEURISTIC_MAX_ROUNDS)
+            }
+            SearchMode::Exact => self.exact_search(&candidates),
+            SearchMode::Heuristic { max_rounds } => self.heuristic_search(&candidates, max_rounds),
+        };
+
+        selected
+            .into_iter()
+            .map(|tx| {
+                self.utxo_pool.apply_tx(tx);
+                tx.clone()
+            })
+            .collect()
+    }
+
+    /// Repeatedly accepts the highest-fee tx valid against an overlay on the handler's pool,
+    /// leaving the pool itself unchanged.  Returns the selected txs in acceptance order.
+    fn heuristic_search<'a>(&self, candidates: &[&'a Tx], max_rounds: usize) -> Vec<&'a Tx> {
+        let mut pool = UtxoPoolOverlay::new(&self.utxo_pool);
+        let mut pending = candidates.to_vec();
+        let mut selected = Vec::new();
+
+        for _ in 0..max_rounds {
+            let mut valid = pending
+                .iter()
+                .filter_map(|tx| {
+                    tx_handler::checked_fee(&pool, tx)
+                        .ok()
+                        .map(|fee| (fee, *tx))
+                })
+                .collect::<Vec<_>>();
+            if valid.is_empty() {
+                break;
+            }
+            valid.sort_by(|(lhs_fee, lhs), (rhs_fee, rhs)| {
+                rhs_fee.cmp(lhs_fee).then_with(|| {
+                    (lhs.hash(), lhs.witness_hash()).cmp(&(rhs.hash(), rhs.witness_hash()))
+                })
+            });
+
+            let mut accepted = HashSet::new();
+            for (_, tx) in valid {
+                // Re-check: a higher-fee tx accepted this round may have spent a shared `Utxo`
+                if tx_handler::checked_fee(&pool, tx).is_ok() {
+                    pool.apply_tx(tx);
+                    accepted.insert(tx.witness_hash());
+                    selected.push(tx);
+                }
+            }
+            pending.retain(|tx| !accepted.contains(tx.witness_hash()));
+        }
+        selected
+    }
+
+    /// Branch-and-bound search over every mutually valid subset of `candidates`.  Returns the
+    /// selected txs with parents ahead of their children.
+    fn exact_search<'a>(&self, candidates: &[&'a Tx]) -> Vec<&'a Tx> {
+        // Each tx's validity and fee are fixed once the outputs it spends are known, so check
+        // every candidate against the pool extended with the outputs of every valid candidate.
+        // Outputs are added only once their tx is valid (repeating until no more candidates
+        // become valid), so no tx can spend the output of an invalid one.
+        let mut extended_pool = UtxoPoolOverlay::new(&self.utxo_pool);
+        let mut valid = HashSet::new();
+        loop {
+            let newly_valid = candidates
+                .iter()
+                .filter(|tx| {
+                    !valid.contains(tx.witness_hash())
+                        && tx_handler::checked_fee(&extended_pool, tx).is_ok()
+                })
+                .collect::<Vec<_>>();
+            if newly_valid.is_empty() {
+                break;
+            }
+            for tx in newly_valid {
+                extended_pool.add_tx_outputs(tx);
+                valid.insert(tx.witness_hash());
+            }
+        }
+
+        let mut nodes = candidates
+            .iter()
+            .filter_map(|tx| {
+                tx_handler::checked_fee(&extended_pool, tx)
+                    .ok()
+                    .map(|fee| Candidate {
+                        tx,
+                        fee,
+                        claims: tx.spent_utxos().collect(),
+                        parents: Vec::new(),
+                    })
+            })
+            .collect::<Vec<_>>();
+        let mut producers = HashMap::<&TxHash, Vec<usize>>::new();
+        for (position, node) in nodes.iter().enumerate() {
+            producers.entry(node.tx.hash()).or_default().push(position);
+        }
+        for node in &mut nodes {
+            let mut parent_hashes = node
+                .claims
+                .iter()
+                .map(Utxo::tx_hash)
+                .filter(|tx_hash| producers.contains_key(tx_hash))
+                .collect::<Vec<_>>();
+            parent_hashes.sort_unstable();
+            parent_hashes.dedup();
+            node.parents = parent_hashes
+                .into_iter()
+                .filter_map(|tx_hash| producers.get(tx_hash).cloned())
+                .collect();
+        }
+
+        let order = topological_order(&nodes);
+        let mut search = ExactSearch::new(&nodes, &order);
+        search.run(&order);
+        search
+            .best
+            .into_iter()
+            .filter_map(|position| nodes.get(position).map(|node| node.tx))
+            .collect()
+    }
+}
+
+/// Orders `nodes` so each node follows every candidate parent (Kahn's algorithm).  Nodes on a dependency
+/// cycle can never be valid and are left out.
+fn topological_order(nodes: &[Candidate<'_>]) -> Vec<usize> {
+    let mut n_unvisited_parents = nodes
+        .iter()
+        .map(|node| node.parents.iter().map(Vec::len).sum::<usize>())
+        .collect::<Vec<_>>();
+    let mut children = vec![Vec::new(); nodes.len()];
+    for (position, node) in nodes.iter().enumerate() {
+        for &parent in node.parents.iter().flatten() {
+            if let Some(siblings) = children.get_mut(parent) {
+                siblings.push(position);
+            }
+        }
+    }
+
+    let mut ready = n_unvisited_parents
+        .iter()
+        .enumerate()
+        .filter(|(_, &n)| n == 0)
+        .map(|(position, _)| position)
+        .collect::<VecDeque<_>>();
+    let mut order = Vec::with_capacity(nodes.len());
+    while let Some(position) = ready.pop_front() {
+        order.push(position);
+        for &child in children.get(position).into_iter().flatten() {
+            if let Some(n) = n_unvisited_parents.get_mut(child) {
+                *n = n.saturating_sub(1);
+                if *n == 0 {
+                    ready.push_back(child);
+                }
+            }
+        }
+    }
+    order
+}
+
+/// State of the exact (branch-and-bound) search
+#[derive(Debug)]
+struct ExactSearch<'n, 'a> {
+    nodes: &'n [Candidate<'a>],
+    /// `remaining_fees[n]` is the total fee of the last `n` nodes in search order (saturating at
+    /// `Decimal::MAX`); bounds what a branch can still add
+    remaining_fees: Vec<Decimal>,
+    included: Vec<bool>,
+    claimed: HashSet<&'n Utxo>,
+    fee: Decimal,
+    selected: Vec<usize>,
+    best_fee: Decimal,
+    best: Vec<usize>,
+}
+
+impl<'n, 'a> ExactSearch<'n, 'a> {
+    fn new(nodes: &'n [Candidate<'a>], order: &[usize]) -> Self {
+        let remaining_fees = std::iter::once(Decimal::new(0, 0))
+            .chain(
+                order
+                    .iter()
+                    .rev()
+                    .scan(Decimal::new(0, 0), |total, &position| {
+                        *total = nodes
+                            .get(position)
+                            .and_then(|node| total.checked_add(node.fee))
+                            .unwrap_or(Decimal::MAX);
+                        Some(*total)
+                    }),
+            )
+            .collect();
+
+        Self {
+            nodes,
+            remaining_fees,
+            included: vec![false; nodes.len()],
+            claimed: HashSet::new(),
+            fee: Decimal::new(0, 0),
+            selected: Vec::new(),
+            best_fee: Decimal::new(0, 0),
+            best: Vec::new(),
+        }
+    }
+
+    /// Explores including and excluding each of the `remaining` nodes in turn
+    fn run(&mut self, remaining: &[usize]) {
+        let bound = self
+            .remaining_fees
+            .get(remaining.len())
+            .map_or(self.fee, |&remaining_fee| {
+                self.fee.checked_add(remaining_fee).unwrap_or(Decimal::MAX)
+            });
+        if bound <= self.best_fee && !self.best.is_empty() {
+            return;
+        }
+
+        let Some((node_position, node, rest)) = remaining
+            .split_first()
+            .and_then(|(&p, rest)| self.nodes.get(p).map(|node| (p, node, rest)))
+        else {
+            if self.fee > self.best_fee || self.best.is_empty() {
+                self.best_fee = self.fee;
+                self.best = self.selected.clone();
+            }
+            return;
+        };
+
+        let parents_included = node.parents.iter().all(|producers| {
+            producers
+                .iter()
+                .any(|&parent| self.included.get(parent).copied().unwrap_or(false))
+        });
+        let claims_free = node.claims.iter().all(|utxo| !self.claimed.contains(utxo));
+        if parents_included && claims_free {
+            let fee = self.fee;
+            self.include(node_position, node);
+            self.run(rest);
+            self.exclude(node_position, node, fee);
+        }
+        self.run(rest);
+    }
+
+    fn include(&mut self, position: usize, node: &'n Candidate<'a>) {
+        if let Some(included) = self.included.get_mut(position) {
+            *included = true;
+        }
+        self.claimed.extend(node.claims.iter());
+        // A mutually valid set's fees sum to at most the value it spends, so this never saturates
+        self.fee = self.fee.checked_add(node.fee).unwrap_or(Decimal::MAX);
+        self.selected.push(position);
+    }
+
+    /// Undoes `include()`, restoring the total fee to `fee`, its value beforehand
+    fn exclude(&mut self, position: usize, node: &Candidate<'a>, fee: Decimal) {
+        if let Some(included) = self.included.get_mut(position) {
+            *included = false;
+        }
+        for utxo in &node.claims {
+            self.claimed.remove(utxo);
+        }
+        self.fee = fee;
+        self.selected.pop();
+    }
+}
@@ -1,5 +1,9 @@
-use crate::consts;
-use serde::{Serialize, Serializer};
+use crate::{consts, Error, Result};
+use ed25519_dalek::SIGNATURE_LENGTH;
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use std::{
     fmt::Formatter,
     fmt::{Debug, Display, Result as FmtResult},
@@ -9,15 +13,31 @@ use std::{
 #[derive(Clone, Debug, Eq)]
 pub struct Signature(pub(crate) ed25519_dalek::Signature);
 
+impl Signature {
+    /// Constructs a `Signature` from its 64-byte encoding, failing if `bytes` is not a well-formed
+    /// ed25519 signature
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ed25519_dalek::Signature::from_bytes(bytes)
+            .map(Self)
+            .map_err(Error::SignatureConstruction)
+    }
+
+    /// Return the 64-byte encoding of this `Signature`
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_LENGTH] {
+        self.0.to_bytes()
+    }
+}
+
 impl Display for Signature {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        self.0.fmt(f)
+        Debug::fmt(&self.0, f)
     }
 }
 
 impl Hash for Signature {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        Hash::hash_slice(&self.0.to_bytes(), state)
+        Hash::hash_slice(&self.0.to_bytes(), state);
     }
 }
 
@@ -38,3 +58,34 @@ impl Serialize for Signature {
         )
     }
 }
+
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as Deserializer<'de>>::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_newtype_struct(consts::ED25519_DALEK_SIGNATURE_TYPE_NAME, SignatureVisitor)
+    }
+}
+
+/// Accepts only the bytes of a well-formed ed25519 signature
+struct SignatureVisitor;
+
+impl<'de> Visitor<'de> for SignatureVisitor {
+    type Value = Signature;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(consts::ED25519_DALEK_SIGNATURE_TYPE_NAME)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        ed25519_dalek::Signature::from_bytes(&bytes)
+            .map(Signature)
+            .map_err(de::Error::custom)
+    }
+}
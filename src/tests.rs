@@ -0,0 +1,122 @@
+use crate::{
+    tx::{PartiallySigned, TxBuilder},
+    KeyPair, OutputTx, PublicKey, Signature, Tx, TxHash, TxIdx, Utxo, UtxoPool,
+};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Serialize};
+
+mod key_pair;
+mod max_fee_tx_handler;
+mod serialization;
+mod tx;
+mod tx_handler;
+mod utxo_pool;
+mod utxo_store;
+
+fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+    let bytes = bincode::serialize(value).unwrap_or_else(|err| panic!("{}", err));
+    bincode::deserialize(&bytes).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn signature() -> Signature {
+    KeyPair::new(&mut rand::thread_rng()).sign(b"scrooge_coin")
+}
+
+fn unsigned_tx(address: &PublicKey) -> TxBuilder {
+    let mut builder = Tx::new();
+    builder
+        .add_input(TxHash([1; 32]), TxIdx(0))
+        .add_output(Decimal::new(42, 1), address.clone());
+    builder
+}
+
+fn tx(address: &PublicKey) -> Tx {
+    build(
+        unsigned_tx(address)
+            .add_signature(signature(), TxIdx(0))
+            .unwrap_or_else(|err| panic!("{}", err)),
+    )
+}
+
+fn build(builder: TxBuilder<PartiallySigned>) -> Tx {
+    builder
+        .into_fully_signed()
+        .unwrap_or_else(|builder| panic!("unsigned inputs: {:?}", builder.unsigned_inputs()))
+        .build()
+        .unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Return output 0 of the tx whose hash is `tag` repeated
+fn utxo(tag: u8) -> Utxo {
+    Utxo::new(TxHash([tag; 32]), TxIdx(0))
+}
+
+/// Return a pool holding each `(Utxo, value, owner)` of `utxos`
+fn pool(utxos: &[(&Utxo, i64, &KeyPair)]) -> UtxoPool {
+    utxos
+        .iter()
+        .fold(UtxoPool::new(), |mut utxo_pool, (utxo, value, owner)| {
+            utxo_pool
+                .add_utxo(
+                    (*utxo).clone(),
+                    OutputTx::new(Decimal::new(*value, 0), owner.public_key()),
+                )
+                .unwrap_or_else(|err| panic!("{}", err));
+            utxo_pool
+        })
+}
+
+/// Return a tx spending each `Utxo` of `inputs`, signed under `SigHash::ALL` by the key paired
+/// with it, and paying each `(value, address)` of `outputs`
+fn spend(inputs: &[(&Utxo, &KeyPair)], outputs: &[(i64, &PublicKey)]) -> Tx {
+    let mut builder = Tx::new();
+    for (utxo, _) in inputs {
+        builder.add_input(utxo.tx_hash().clone(), *utxo.tx_idx());
+    }
+    for (value, address) in outputs {
+        builder.add_output(Decimal::new(*value, 0), (*address).clone());
+    }
+    let (_, first_signer) = inputs
+        .first()
+        .unwrap_or_else(|| panic!("{}", "tx has no inputs"));
+    let mut builder = builder
+        .sign_input(TxIdx(0), first_signer)
+        .unwrap_or_else(|err| panic!("{}", err));
+    for (position, (_, key_pair)) in inputs.iter().enumerate().skip(1) {
+        builder
+            .sign_input(TxIdx::from_position(position), key_pair)
+            .unwrap_or_else(|err| panic!("{}", err));
+    }
+    build(builder)
+}
+
+/// Return the pool's `Utxo`s, sorted
+fn sorted_utxos(utxo_pool: &UtxoPool) -> Vec<Utxo> {
+    let mut utxos = utxo_pool.all_utxos();
+    utxos.sort_by(|lhs, rhs| (lhs.tx_hash(), lhs.tx_idx()).cmp(&(rhs.tx_hash(), rhs.tx_idx())));
+    utxos
+}
+
+/// Adds `utxo(tag)` to `utxo_pool`, paying 1 to `address`
+fn add_tagged(utxo_pool: &mut UtxoPool, tag: u8, address: &PublicKey) {
+    utxo_pool
+        .add_utxo(
+            utxo(tag),
+            OutputTx::new(Decimal::new(1, 0), address.clone()),
+        )
+        .unwrap_or_else(|err| panic!("{}", err));
+}
+
+/// Return a pool holding `utxo(tag)` for each of `tags`, with value `tag`
+fn tagged_pool(tags: impl Iterator<Item = u8>, address: &PublicKey) -> UtxoPool {
+    let mut utxo_pool = UtxoPool::new();
+    for tag in tags {
+        utxo_pool
+            .add_utxo(
+                utxo(tag),
+                OutputTx::new(Decimal::from(tag), address.clone()),
+            )
+            .unwrap_or_else(|err| panic!("{}", err));
+    }
+    utxo_pool
+}
@@ -0,0 +1,109 @@
+use crate::{consts, tx::Address, Error, KeyPair, SecretKey, Signature};
+use sha2::{Digest, Sha512};
+
+fn hex(digits: &str) -> Vec<u8> {
+    (0..digits.len())
+        .step_by(2)
+        .map(|idx| {
+            digits
+                .get(idx..idx.saturating_add(2))
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .unwrap_or_else(|| panic!("bad hex digits: {}", digits))
+        })
+        .collect()
+}
+
+/// Test vectors from RFC 8032, section 7.1: (secret key, public key, message, signature)
+const ED25519_TEST_VECTORS: [(&str, &str, &str, &str); 4] = [
+    (
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+        "",
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9\
+         b46bd25bf5f0595bbe24655141438e7a100b",
+    ),
+    (
+        "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+        "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+        "72",
+        "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f1\
+         1d8c387b2eaeb4302aeeb00d291612bb0c00",
+    ),
+    (
+        "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+        "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+        "af82",
+        "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984d\
+         c6594a7c15e9716ed28dc027beceea1ec40a",
+    ),
+    (
+        "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+        "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3fe\
+         ebbd454d4423643ce80e2a9ac94fa54ca49f",
+        "dc2a4459e7369633a52b1bf277839a00201009a3efbf3ecb69bea2186c26b58909351fc9ac90b3ecfdfbc7c66431\
+         e0303dca179c138ac17ad9bef1177331a704",
+    ),
+];
+
+#[test]
+fn ed25519_matches_rfc_8032_test_vectors() {
+    for (secret_key, public_key, message, signature) in &ED25519_TEST_VECTORS {
+        let secret_key =
+            SecretKey::from_slice(hex(secret_key)).unwrap_or_else(|err| panic!("{}", err));
+        let key_pair = KeyPair::from_secret_key(&secret_key);
+        let message = hex(message);
+        let signature = hex(signature);
+
+        assert_eq!(key_pair.public_key().as_bytes(), &hex(public_key)[..]);
+        assert_eq!(&key_pair.sign(&message).to_bytes()[..], &signature[..]);
+        let signature = Signature::from_bytes(&signature).unwrap_or_else(|err| panic!("{}", err));
+        assert!(key_pair.public_key().verify(&message, &signature).is_ok());
+        let tampered = message.iter().chain(&[0]).copied().collect::<Vec<_>>();
+        assert!(key_pair.public_key().verify(&tampered, &signature).is_err());
+    }
+}
+
+#[test]
+fn ed25519ph_matches_rfc_8032_test_vector() {
+    // RFC 8032, section 7.3
+    let secret_key = SecretKey::from_slice(hex(
+        "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+    ))
+    .unwrap_or_else(|err| panic!("{}", err));
+    let key_pair = KeyPair::from_secret_key(&secret_key);
+    let signature = hex(
+        "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062ad\
+         f5aaa10b8c61e636062aaad11c2a26083406",
+    );
+    let prehashed_message = || {
+        let mut hasher = Sha512::new();
+        hasher.update(b"abc");
+        hasher
+    };
+
+    let signed = key_pair
+        .sign_prehashed(prehashed_message(), None)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(&signed.to_bytes()[..], &signature[..]);
+    assert!(key_pair
+        .public_key()
+        .verify_prehashed(prehashed_message(), None, &signed)
+        .is_ok());
+    assert!(key_pair
+        .public_key()
+        .verify_prehashed(prehashed_message(), Some(b"ctx"), &signed)
+        .is_err());
+    assert!(key_pair.public_key().verify(b"abc", &signed).is_err());
+    let context = vec![0; consts::ED25519PH_MAX_CONTEXT_LEN + 1];
+    assert!(matches!(
+        key_pair.sign_prehashed(prehashed_message(), Some(&context)),
+        Err(Error::Ed25519phContextTooLong(256))
+    ));
+    assert!(matches!(
+        key_pair
+            .public_key()
+            .verify_prehashed(prehashed_message(), Some(&context), &signed),
+        Err(Error::Ed25519phContextTooLong(256))
+    ));
+}
@@ -0,0 +1,113 @@
+use super::{build, pool, spend, utxo};
+use crate::{
+    KeyPair, MaxFeeTxHandler, OutputTx, SearchMode, SigHash, Tx, TxHash, TxIdx, Utxo, UtxoPool,
+};
+use rust_decimal::Decimal;
+
+/// Return the hashes of `txs`, sorted
+fn sorted_hashes(txs: &[Tx]) -> Vec<TxHash> {
+    let mut hashes = txs.iter().map(|tx| tx.hash().clone()).collect::<Vec<_>>();
+    hashes.sort();
+    hashes
+}
+
+#[test]
+fn max_fee_tx_handler_never_selects_child_of_invalid_tx() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let mallory = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    // `forged` is signed by the wrong key; `child` is validly signed but spends its output
+    let forged = spend(&[(&utxo(1), &mallory)], &[(5, &mallory.public_key())]);
+    let child = spend(
+        &[(&Utxo::new(forged.hash().clone(), TxIdx(0)), &mallory)],
+        &[(1, &mallory.public_key())],
+    );
+
+    for search_mode in &[SearchMode::Exact, SearchMode::Heuristic { max_rounds: 4 }] {
+        let mut tx_handler = MaxFeeTxHandler::new(&utxo_pool);
+        tx_handler.set_search_mode(*search_mode);
+        assert!(tx_handler
+            .handle_txs(&[forged.clone(), child.clone()])
+            .is_empty());
+        assert_eq!(tx_handler.utxo_pool().all_utxos(), vec![utxo(1)]);
+    }
+}
+
+#[test]
+fn max_fee_tx_handler_exact_search_beats_greedy_heuristic() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let address = alice.public_key();
+    let utxo_pool = pool(&[(&utxo(1), 10, &alice), (&utxo(2), 10, &alice)]);
+    // `both` pays the highest single fee (5), but `first` and `second` together pay 7
+    let both = spend(&[(&utxo(1), &alice), (&utxo(2), &alice)], &[(15, &address)]);
+    let first = spend(&[(&utxo(1), &alice)], &[(6, &address)]);
+    let second = spend(&[(&utxo(2), &alice)], &[(7, &address)]);
+    let txs = [both.clone(), first.clone(), second.clone()];
+
+    let select = |search_mode| {
+        let mut tx_handler = MaxFeeTxHandler::new(&utxo_pool);
+        tx_handler.set_search_mode(search_mode);
+        sorted_hashes(&tx_handler.handle_txs(&txs))
+    };
+    let exact = sorted_hashes(&[first, second]);
+    assert_eq!(select(SearchMode::Exact), exact);
+    assert_eq!(select(SearchMode::Auto), exact);
+    assert_eq!(
+        select(SearchMode::Heuristic { max_rounds: 4 }),
+        sorted_hashes(&[both])
+    );
+}
+
+#[test]
+fn max_fee_tx_handler_handles_fees_summing_past_decimal_max() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let address = alice.public_key();
+    let mut utxo_pool = UtxoPool::new();
+    utxo_pool
+        .add_utxo(utxo(1), OutputTx::new(Decimal::MAX, address.clone()))
+        .unwrap_or_else(|err| panic!("{}", err));
+    // Both txs spend `utxo(1)`, so only one can be selected, but their fees sum past `MAX`
+    let higher = spend(&[(&utxo(1), &alice)], &[(1, &address)]);
+    let lower = spend(&[(&utxo(1), &alice)], &[(2, &address)]);
+
+    for search_mode in &[SearchMode::Exact, SearchMode::Heuristic { max_rounds: 4 }] {
+        let mut tx_handler = MaxFeeTxHandler::new(&utxo_pool);
+        tx_handler.set_search_mode(*search_mode);
+        assert_eq!(
+            sorted_hashes(&tx_handler.handle_txs(&[lower.clone(), higher.clone()])),
+            vec![higher.hash().clone()]
+        );
+    }
+}
+
+#[test]
+fn max_fee_tx_handler_lets_either_same_hash_variant_fund_a_child() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let address = alice.public_key();
+    let utxo_pool = pool(&[(&utxo(1), 10, &alice)]);
+    // `parent` and `variant` differ only in their signatures, so share a hash and outputs
+    let parent = spend(&[(&utxo(1), &alice)], &[(9, &address)]);
+    let mut builder = Tx::new();
+    builder
+        .add_input(utxo(1).tx_hash().clone(), *utxo(1).tx_idx())
+        .add_output(Decimal::new(9, 0), address.clone());
+    let variant = build(
+        builder
+            .sign_input_with_sighash(TxIdx(0), SigHash::ALL.with_anyone_can_pay(), &alice)
+            .unwrap_or_else(|err| panic!("{}", err)),
+    );
+    assert_eq!(variant.hash(), parent.hash());
+    assert_ne!(variant.witness_hash(), parent.witness_hash());
+    let child = spend(
+        &[(&Utxo::new(parent.hash().clone(), TxIdx(0)), &alice)],
+        &[(8, &address)],
+    );
+
+    for search_mode in &[SearchMode::Exact, SearchMode::Heuristic { max_rounds: 4 }] {
+        let mut tx_handler = MaxFeeTxHandler::new(&utxo_pool);
+        tx_handler.set_search_mode(*search_mode);
+        let accepted = tx_handler.handle_txs(&[parent.clone(), variant.clone(), child.clone()]);
+        assert_eq!(accepted.len(), 2);
+        assert!(accepted.iter().any(|tx| tx.hash() == child.hash()));
+    }
+}
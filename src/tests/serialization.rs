@@ -0,0 +1,98 @@
+use super::{round_trip, signature, tagged_pool, tx};
+use crate::{
+    tx::Address, InputTx, KeyPair, OutputTx, SecretKey, Tx, TxHash, TxIdx, Utxo, UtxoPool,
+};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+fn bincode_bytes<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+    bincode::serialize(value).unwrap_or_else(|err| panic!("{}", err))
+}
+
+#[test]
+fn bincode_round_trips_key_types() {
+    let key_pair = KeyPair::new(&mut rand::thread_rng());
+    let public_key = key_pair.public_key();
+    let secret_key = key_pair.secret_key();
+
+    assert_eq!(round_trip(&public_key), public_key);
+    assert_eq!(round_trip::<SecretKey>(&secret_key), secret_key);
+    assert_eq!(round_trip(&key_pair).public_key(), public_key);
+    let signature = signature();
+    assert_eq!(round_trip(&signature), signature);
+}
+
+#[test]
+fn bincode_round_trips_tx_types() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let tx_hash = TxHash([7; 32]);
+    let tx_idx = TxIdx(3);
+    let utxo = Utxo::new(tx_hash.clone(), tx_idx);
+    let output_tx = OutputTx::new(Decimal::new(-15, 2), address.clone());
+
+    assert_eq!(round_trip(&tx_hash), tx_hash);
+    assert_eq!(round_trip(&tx_idx), tx_idx);
+    assert_eq!(round_trip(&utxo), utxo);
+    assert_eq!(round_trip(&output_tx), output_tx);
+    for input_tx in &[
+        InputTx::Genesis,
+        InputTx::new(tx_hash.clone(), tx_idx),
+        InputTx::new(tx_hash, tx_idx)
+            .sign(signature())
+            .unwrap_or_else(|err| panic!("{}", err)),
+    ] {
+        assert_eq!(&round_trip(input_tx), input_tx);
+    }
+
+    let tx = tx(&address);
+    let decoded = round_trip(&tx);
+    assert_eq!(decoded.hash(), tx.hash());
+    assert_eq!(bincode_bytes(&decoded), bincode_bytes(&tx));
+}
+
+#[test]
+fn bincode_round_trips_pool_types() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let utxo_pool = tagged_pool(0..4, &address);
+
+    let decoded = round_trip(&utxo_pool);
+    assert_eq!(decoded.commitment(), utxo_pool.commitment());
+    assert!(decoded.diff(&utxo_pool).is_empty());
+    let delta = UtxoPool::new().diff(&utxo_pool);
+    assert_eq!(round_trip(&delta), delta);
+}
+
+#[test]
+fn deserializing_tx_with_wrong_hash_fails() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut bytes = bincode_bytes(&tx(&address));
+    // The hash is serialized first, after its 8-byte length prefix
+    if let Some(byte) = bytes.get_mut(8) {
+        *byte ^= 0xff;
+    }
+    assert!(bincode::deserialize::<Tx>(&bytes).is_err());
+}
+
+#[test]
+fn deserializing_mismatched_key_pair_fails() {
+    let secret_key = KeyPair::new(&mut rand::thread_rng()).secret_key();
+    let public_key = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let bytes = secret_key
+        .as_bytes()
+        .iter()
+        .chain(public_key.as_bytes())
+        .copied()
+        .collect::<Vec<_>>();
+    assert!(bincode::deserialize::<KeyPair>(&bincode_bytes(&bytes)).is_err());
+}
+
+#[test]
+fn deserializing_pool_with_duplicate_utxo_fails() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let entry = (
+        Utxo::new(TxHash([1; 32]), TxIdx(0)),
+        OutputTx::new(Decimal::new(1, 0), address),
+    );
+    let bytes = bincode_bytes(&[entry.clone(), entry][..]);
+    assert!(bincode::deserialize::<UtxoPool>(&bytes).is_err());
+}
@@ -0,0 +1,511 @@
+use super::{build, pool, round_trip, signature, spend, tx, unsigned_tx, utxo};
+use crate::{
+    Error, InputTx, KeyPair, Operation, OutputTx, PartiallySignedTx, SigHash, SigHashOutputs,
+    Signature, Tx, TxHandler, TxHash, TxIdx, TxValidationError, Utxo, UtxoPool,
+};
+use rust_decimal::Decimal;
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
+
+#[test]
+fn canonical_encoding_round_trips_and_is_hashed() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let tx = tx(&address);
+    let bytes = tx.encode();
+    assert_eq!(bytes.get(..4), Some(&b"SCRG"[..]));
+
+    let decoded = Tx::decode(&bytes).unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(decoded.hash(), tx.hash());
+    assert_eq!(decoded.encode(), bytes);
+    assert_eq!(decoded.witness_hash(), tx.witness_hash());
+    assert_eq!(tx.witness_hash().as_ref(), &*Sha256::digest(&bytes));
+}
+
+#[test]
+fn re_signing_tx_changes_witness_hash_but_not_hash() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let tx = tx(&address);
+    let re_signed = self::tx(&address);
+    assert_ne!(re_signed.encode(), tx.encode());
+    assert_ne!(re_signed.witness_hash(), tx.witness_hash());
+    assert_eq!(re_signed.hash(), tx.hash());
+
+    let unsigned = unsigned_tx(&address)
+        .into_tx()
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(unsigned.hash(), tx.hash());
+    assert_eq!(unsigned.hash(), unsigned.witness_hash());
+}
+
+#[test]
+fn decoding_non_canonical_tx_fails() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut builder = Tx::new();
+    builder
+        .add_input(TxHash([1; 32]), TxIdx(0))
+        .add_output(Decimal::new(10, 0), address);
+    let bytes = builder
+        .into_tx()
+        .unwrap_or_else(|err| panic!("{}", err))
+        .encode();
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(matches!(
+        Tx::decode(&trailing),
+        Err(Error::MalformedTxEncoding(_))
+    ));
+    assert!(matches!(
+        Tx::decode(
+            bytes
+                .get(..bytes.len().saturating_sub(1))
+                .unwrap_or_default()
+        ),
+        Err(Error::MalformedTxEncoding(_))
+    ));
+
+    // 10 is encoded normalized, as scale 0; re-encode it as 100 at scale 1.  The 14-byte value is
+    // followed by the 8-byte length and 32 bytes of the address.
+    let value_at = bytes.len().saturating_sub(54);
+    let mut unnormalized = bytes.clone();
+    if let Some(value) = unnormalized.get_mut(value_at..value_at.saturating_add(14)) {
+        value.copy_from_slice(&[0, 1, 100, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+    assert!(matches!(
+        Tx::decode(&unnormalized),
+        Err(Error::NonCanonicalTxEncoding)
+    ));
+
+    let mut future = bytes;
+    if let Some(version) = future.get_mut(4) {
+        *version = 2;
+    }
+    assert!(matches!(
+        Tx::decode(&future),
+        Err(Error::UnsupportedTxEncodingVersion(2))
+    ));
+}
+
+#[test]
+fn sighash_flags_select_what_signatures_cover() {
+    let key_pair = KeyPair::new(&mut rand::thread_rng());
+    let address = key_pair.public_key();
+    let tx_handler = TxHandler::new(&pool(&[(&utxo(1), 5, &key_pair), (&utxo(2), 5, &key_pair)]));
+    // Signs input 0 under `sighash`, then adds a second input and output before signing input 1.
+    // Fails with the inputs whose signatures the additions invalidated, if any.
+    let validate = |sighash: SigHash| {
+        let mut builder = Tx::new();
+        builder
+            .add_input(TxHash([1; 32]), TxIdx(0))
+            .add_output(Decimal::new(4, 0), address.clone());
+        let mut builder = builder
+            .sign_input_with_sighash(TxIdx(0), sighash, &key_pair)
+            .unwrap_or_else(|err| panic!("{}", err));
+        builder
+            .add_input(TxHash([2; 32]), TxIdx(0))
+            .add_output(Decimal::new(4, 0), address.clone());
+        builder
+            .sign_input_with_sighash(TxIdx(1), SigHash::ALL, &key_pair)
+            .unwrap_or_else(|err| panic!("{}", err));
+        builder
+            .into_fully_signed()
+            .map(|builder| {
+                tx_handler.validate_tx(&builder.build().unwrap_or_else(|err| panic!("{}", err)))
+            })
+            .map_err(|builder| builder.unsigned_inputs())
+    };
+
+    let stale = Err(vec![TxIdx(0)]);
+    assert_eq!(validate(SigHash::ALL), stale);
+    assert_eq!(validate(SigHash::ALL.with_anyone_can_pay()), stale);
+    assert_eq!(validate(SigHash::NONE), stale);
+    assert_eq!(validate(SigHash::NONE.with_anyone_can_pay()), Ok(Ok(())));
+    assert_eq!(validate(SigHash::SINGLE.with_anyone_can_pay()), Ok(Ok(())));
+}
+
+#[test]
+fn sighash_single_requires_matching_output() {
+    let key_pair = KeyPair::new(&mut rand::thread_rng());
+    let mut builder = Tx::new();
+    builder
+        .add_input(TxHash([1; 32]), TxIdx(0))
+        .add_input(TxHash([2; 32]), TxIdx(0))
+        .add_output(Decimal::new(1, 0), key_pair.public_key());
+    assert!(matches!(
+        builder.signing_message(TxIdx(1), SigHash::SINGLE),
+        Err(Error::SigHashSingleWithoutOutput(_))
+    ));
+
+    let utxo_pool = pool(&[(&utxo(1), 5, &key_pair), (&utxo(2), 5, &key_pair)]);
+    let mut builder = builder
+        .sign_input_with_sighash(TxIdx(0), SigHash::ALL, &key_pair)
+        .unwrap_or_else(|err| panic!("{}", err));
+    builder
+        .add_signature_with_sighash(signature(), SigHash::SINGLE, TxIdx(1))
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(
+        TxHandler::new(&utxo_pool).validate_tx(&build(builder)),
+        Err(TxValidationError::SigHashSingleWithoutOutput { input: TxIdx(1) })
+    );
+}
+
+#[test]
+fn sighash_byte_encoding_round_trips() {
+    for outputs in &[
+        SigHashOutputs::All,
+        SigHashOutputs::None,
+        SigHashOutputs::Single,
+    ] {
+        for anyone_can_pay in &[false, true] {
+            let sighash = SigHash::new(*outputs, *anyone_can_pay);
+            assert_eq!(SigHash::from_byte(sighash.to_byte()), Some(sighash));
+        }
+    }
+    assert_eq!(SigHash::ALL.to_byte(), 0x01);
+    assert_eq!(SigHash::SINGLE.with_anyone_can_pay().to_byte(), 0x83);
+    assert_eq!(SigHash::from_byte(0x00), None);
+    assert_eq!(SigHash::from_byte(0x04), None);
+}
+
+#[test]
+fn signed_tx_built_end_to_end_is_valid() {
+    let key_pair = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &key_pair)]);
+    let tx = spend(&[(&utxo(1), &key_pair)], &[(5, &key_pair.public_key())]);
+    assert_eq!(TxHandler::new(&utxo_pool).validate_tx(&tx), Ok(()));
+
+    let signature = tx
+        .input_tx(TxIdx(0))
+        .and_then(InputTx::signature)
+        .cloned()
+        .unwrap_or_else(|| panic!("{}", "input is not signed"));
+    assert_eq!(
+        Signature::from_bytes(&signature.to_bytes()).ok(),
+        Some(signature)
+    );
+    assert!(matches!(
+        Signature::from_bytes(&[0; 3]),
+        Err(Error::SignatureConstruction(_))
+    ));
+    assert!(matches!(
+        Tx::new().sign_input(TxIdx(0), &key_pair),
+        Err(Error::TxnIdxOutOfBounds(_))
+    ));
+}
+
+#[test]
+fn partially_signed_tx_collects_signatures_from_several_key_holders() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let bob = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice), (&utxo(2), 5, &bob)]);
+    let mut builder = Tx::new();
+    builder
+        .add_input(TxHash([1; 32]), TxIdx(0))
+        .add_input(TxHash([2; 32]), TxIdx(0))
+        .add_output(Decimal::new(10, 0), alice.public_key());
+    let created =
+        PartiallySignedTx::new(builder, &utxo_pool).unwrap_or_else(|err| panic!("{}", err));
+
+    // Each holder signs a copy received over the wire
+    let mut alices = round_trip(&created);
+    let mut bobs = round_trip(&created);
+    assert_eq!(alices.sign(&alice), 1);
+    assert_eq!(bobs.sign(&bob), 1);
+    assert_eq!(alices.sign(&alice), 0);
+    assert_eq!(
+        alices.missing_signatures(),
+        vec![(TxIdx(1), &bob.public_key())]
+    );
+    assert!(matches!(
+        alices.clone().finalize(),
+        Err(Error::PartiallySignedTxIncomplete(missing)) if missing == vec![TxIdx(1)]
+    ));
+
+    let mut combined = round_trip(&alices);
+    combined
+        .combine(&bobs)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert!(combined.is_complete());
+    assert!(combined.missing_signatures().is_empty());
+    let tx = combined.finalize().unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(tx.hash(), created.tx().hash());
+    assert_eq!(TxHandler::new(&utxo_pool).validate_tx(&tx), Ok(()));
+
+    let other = PartiallySignedTx::new(
+        unsigned_tx(&alice.public_key()),
+        &pool(&[(&utxo(1), 5, &alice)]),
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+    assert!(matches!(
+        alices.combine(&other),
+        Err(Error::PartiallySignedTxMismatch)
+    ));
+    assert!(matches!(
+        PartiallySignedTx::new(unsigned_tx(&alice.public_key()), &UtxoPool::new()),
+        Err(Error::MissingUtxo(_))
+    ));
+}
+
+#[test]
+fn partially_signed_tx_replaces_and_never_copies_invalid_signatures() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let bob = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice), (&utxo(2), 5, &bob)]);
+    // Return the tx spending both `Utxo`s, carrying a signature by an unrelated key on `forged`
+    let created = |forged: Option<TxIdx>| {
+        let mut builder = Tx::new();
+        builder
+            .add_input(utxo(1).tx_hash().clone(), TxIdx(0))
+            .add_input(utxo(2).tx_hash().clone(), TxIdx(0))
+            .add_output(Decimal::new(10, 0), alice.public_key());
+        match forged {
+            Some(idx) => PartiallySignedTx::new(
+                builder
+                    .add_signature(signature(), idx)
+                    .unwrap_or_else(|err| panic!("{}", err)),
+                &utxo_pool,
+            ),
+            None => PartiallySignedTx::new(builder, &utxo_pool),
+        }
+        .unwrap_or_else(|err| panic!("{}", err))
+    };
+
+    // A forged signature counts as missing, and signing replaces it
+    let mut alices = created(Some(TxIdx(0)));
+    assert_eq!(alices.missing_signatures().len(), 2);
+    assert_eq!(alices.sign(&alice), 1);
+    assert_eq!(alices.sign(&alice), 0);
+    assert_eq!(
+        alices.missing_signatures(),
+        vec![(TxIdx(1), &bob.public_key())]
+    );
+
+    // Combining skips a forged signature in `other`, and fills a forged one in this copy
+    alices
+        .combine(&created(Some(TxIdx(1))))
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(
+        alices.missing_signatures(),
+        vec![(TxIdx(1), &bob.public_key())]
+    );
+    let mut bobs = created(Some(TxIdx(1)));
+    assert_eq!(bobs.sign(&bob), 1);
+    let mut combined = created(Some(TxIdx(0)));
+    combined
+        .combine(&alices)
+        .and_then(|combined| combined.combine(&bobs))
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert!(combined.is_complete());
+    let tx = combined.finalize().unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(TxHandler::new(&utxo_pool).validate_tx(&tx), Ok(()));
+}
+
+#[test]
+fn tx_builder_tracks_signing_state_and_logs_operations() {
+    let key_pair = KeyPair::new(&mut rand::thread_rng());
+    let address = key_pair.public_key();
+    let mut builder = unsigned_tx(&address);
+    builder.add_input(TxHash([2; 32]), TxIdx(0));
+    let builder = builder
+        .sign_input(TxIdx(0), &key_pair)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(builder.unsigned_inputs(), vec![TxIdx(1)]);
+
+    let mut builder = builder
+        .into_fully_signed()
+        .err()
+        .unwrap_or_else(|| panic!("{}", "input 1 is unsigned"));
+    builder
+        .sign_input(TxIdx(1), &key_pair)
+        .and_then(|builder| builder.unsign_input(TxIdx(0)))
+        .and_then(|builder| builder.sign_input(TxIdx(0), &key_pair))
+        .unwrap_or_else(|err| panic!("{}", err));
+    let tx_hash = builder
+        .clone()
+        .into_tx()
+        .unwrap_or_else(|err| panic!("{}", err))
+        .hash()
+        .clone();
+    assert_eq!(
+        builder.operations(),
+        &[
+            Operation::AddInput(Utxo::new(TxHash([1; 32]), TxIdx(0))),
+            Operation::AddOutput(OutputTx::new(Decimal::new(42, 1), address)),
+            Operation::AddInput(Utxo::new(TxHash([2; 32]), TxIdx(0))),
+            Operation::Sign(TxIdx(0), SigHash::ALL, tx_hash.clone()),
+            Operation::Sign(TxIdx(1), SigHash::ALL, tx_hash.clone()),
+            Operation::Unsign(TxIdx(0)),
+            Operation::Sign(TxIdx(0), SigHash::ALL, tx_hash.clone()),
+        ][..]
+    );
+    let tx = build(builder);
+    assert_eq!(tx.hash(), &tx_hash);
+}
+
+#[test]
+fn tx_builder_removes_signatures_a_modification_invalidates() {
+    let key_pair = KeyPair::new(&mut rand::thread_rng());
+    let address = key_pair.public_key();
+    let mut builder = unsigned_tx(&address);
+    builder.add_input(TxHash([2; 32]), TxIdx(0));
+    let mut builder = builder
+        .sign_input(TxIdx(0), &key_pair)
+        .unwrap_or_else(|err| panic!("{}", err));
+    builder
+        .sign_input_with_sighash(TxIdx(1), SigHash::NONE.with_anyone_can_pay(), &key_pair)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    // Input 0's `SigHash::ALL` signature covers the new output; input 1's covers neither it nor
+    // the new input, so survives both
+    builder.add_output(Decimal::new(1, 0), address);
+    assert_eq!(builder.unsigned_inputs(), vec![TxIdx(0)]);
+    assert_eq!(
+        builder.operations().last(),
+        Some(&Operation::Unsign(TxIdx(0)))
+    );
+    let mut builder = builder
+        .into_fully_signed()
+        .err()
+        .unwrap_or_else(|| panic!("{}", "input 0's signature is stale"));
+    builder
+        .sign_input(TxIdx(0), &key_pair)
+        .unwrap_or_else(|err| panic!("{}", err));
+    builder.add_input(TxHash([3; 32]), TxIdx(0));
+    assert_eq!(builder.unsigned_inputs(), vec![TxIdx(0), TxIdx(2)]);
+}
+
+#[test]
+fn tx_inspects_and_verifies_itself_against_utxo_pool() {
+    let key_pair = KeyPair::new(&mut rand::thread_rng());
+    let address = key_pair.public_key();
+    let utxo = utxo(1);
+    let utxo_pool = pool(&[(&utxo, 5, &key_pair)]);
+
+    let mut builder = unsigned_tx(&address);
+    builder.add_output(Decimal::new(3, 1), address.clone());
+    let tx = build(
+        builder
+            .sign_input(TxIdx(0), &key_pair)
+            .unwrap_or_else(|err| panic!("{}", err)),
+    );
+    assert_eq!((tx.n_input_txs(), tx.n_output_txs()), (1, 2));
+    assert_eq!(tx.inputs().count(), 1);
+    assert_eq!(
+        tx.outputs().map(OutputTx::value).collect::<Vec<_>>(),
+        vec![Decimal::new(42, 1), Decimal::new(3, 1)]
+    );
+    assert_eq!(
+        tx.input_tx(TxIdx(0)).and_then(InputTx::utxo),
+        Some(utxo.clone())
+    );
+    assert!(tx.output_tx(TxIdx(2)).is_none());
+    assert_eq!(tx.total_output(), Ok(Decimal::new(45, 1)));
+    assert_eq!(tx.fee(&utxo_pool), Ok(Decimal::new(5, 1)));
+    assert_eq!(tx.verify_signatures(&utxo_pool), Ok(()));
+    assert_eq!(tx.spent_utxos().collect::<Vec<_>>(), vec![utxo]);
+    assert_eq!(
+        tx.created_utxos().collect::<Vec<_>>(),
+        vec![
+            Utxo::new(tx.hash().clone(), TxIdx(0)),
+            Utxo::new(tx.hash().clone(), TxIdx(1)),
+        ]
+    );
+
+    let forged = build(
+        unsigned_tx(&address)
+            .add_signature(signature(), TxIdx(0))
+            .unwrap_or_else(|err| panic!("{}", err)),
+    );
+    assert_eq!(
+        forged.verify_signatures(&utxo_pool),
+        Err(TxValidationError::BadSignature { input: TxIdx(0) })
+    );
+    assert!(matches!(
+        tx.fee(&UtxoPool::new()),
+        Err(TxValidationError::MissingUtxo(_))
+    ));
+}
+
+#[test]
+fn tx_hash_and_utxo_round_trip_through_hex_and_outpoint_form() {
+    let tx_hash = TxHash::new([0xab; 32]);
+    let hex = "ab".repeat(32);
+    assert_eq!(tx_hash.to_string(), hex);
+    assert_eq!(hex.parse::<TxHash>().ok(), Some(tx_hash.clone()));
+    assert_eq!(
+        hex.to_uppercase().parse::<TxHash>().ok(),
+        Some(tx_hash.clone())
+    );
+    assert_eq!(
+        TxHash::try_from(tx_hash.as_ref()).ok(),
+        Some(tx_hash.clone())
+    );
+    assert!(matches!(
+        TxHash::try_from(&[0_u8; 31][..]),
+        Err(Error::TxHashLength(31))
+    ));
+    for bad in &[
+        "ab".repeat(31),
+        "ab".repeat(33),
+        format!("+b{}", "ab".repeat(31)),
+    ] {
+        assert!(matches!(bad.parse::<TxHash>(), Err(Error::ParseTxHash(_))));
+    }
+
+    let utxo = Utxo::new(tx_hash, TxIdx(7));
+    let outpoint = format!("{hex}:7");
+    assert_eq!(utxo.to_string(), outpoint);
+    assert_eq!(outpoint.parse::<Utxo>().ok(), Some(utxo));
+    for bad in &[
+        hex.clone(),
+        format!("{hex}:"),
+        format!("{hex}:+7"),
+        format!("{hex}:7:1"),
+    ] {
+        assert!(matches!(bad.parse::<Utxo>(), Err(Error::ParseUtxo(_))));
+    }
+}
+
+#[test]
+fn tx_idx_converts_checked_and_iterates_over_tx_indices() {
+    assert_eq!(TxIdx::new(3), TxIdx::from(3_u32));
+    assert_eq!(TxIdx::try_from(3_usize).ok(), Some(TxIdx::new(3)));
+    assert_eq!(usize::try_from(TxIdx::new(3)).ok(), Some(3));
+    assert_eq!(u64::from(TxIdx::new(3)), 3);
+    assert_eq!(TxIdx::new(3).to_string(), "3");
+    if usize::try_from(u64::MAX).is_err() {
+        assert!(matches!(
+            usize::try_from(TxIdx::new(u64::MAX)),
+            Err(Error::TxIdxExceedsUsize(_))
+        ));
+    }
+
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut builder = unsigned_tx(&address);
+    builder.add_output(Decimal::new(1, 0), address);
+    assert_eq!(
+        builder.input_idxs().collect::<Vec<_>>(),
+        vec![TxIdx::new(0)]
+    );
+    assert_eq!(
+        builder.output_idxs().collect::<Vec<_>>(),
+        vec![TxIdx::new(0), TxIdx::new(1)]
+    );
+    assert!(builder.input_tx(TxIdx::new(u64::MAX)).is_none());
+    assert!(matches!(
+        builder.signing_message(TxIdx::new(u64::MAX), SigHash::ALL),
+        Err(Error::TxnIdxOutOfBounds(_))
+    ));
+    let tx = build(
+        builder
+            .add_signature(signature(), TxIdx::new(0))
+            .unwrap_or_else(|err| panic!("{}", err)),
+    );
+    assert_eq!(tx.input_idxs().count(), tx.n_input_txs());
+    assert_eq!(
+        tx.output_idxs()
+            .filter_map(|idx| tx.output_tx(idx))
+            .collect::<Vec<_>>(),
+        tx.outputs().collect::<Vec<_>>()
+    );
+}
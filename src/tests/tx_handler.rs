@@ -0,0 +1,387 @@
+use super::{build, pool, spend, unsigned_tx, utxo};
+use crate::{
+    ConflictPolicy, Error, FirstSeen, HighestFee, HighestFeeRate, InputTx, KeyPair, LowestTxHash,
+    OutputTx, ProposedTx, RejectionReason, Tx, TxHandler, TxHash, TxIdx, TxValidationError, Utxo,
+    UtxoPool,
+};
+use rust_decimal::Decimal;
+
+#[test]
+fn validate_tx_accepts_tx_satisfying_every_rule() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let bob = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice), (&utxo(2), 3, &bob)]);
+    let tx = spend(
+        &[(&utxo(1), &alice), (&utxo(2), &bob)],
+        &[(6, &bob.public_key()), (2, &alice.public_key())],
+    );
+    let tx_handler = TxHandler::new(&utxo_pool);
+    assert_eq!(tx_handler.validate_tx(&tx), Ok(()));
+    assert!(tx_handler.is_valid_tx(&tx));
+}
+
+#[test]
+fn validate_tx_rejects_claim_on_utxo_not_in_pool() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    let tx = spend(&[(&utxo(2), &alice)], &[(5, &alice.public_key())]);
+    let tx_handler = TxHandler::new(&utxo_pool);
+    assert_eq!(
+        tx_handler.validate_tx(&tx),
+        Err(TxValidationError::MissingUtxo(utxo(2)))
+    );
+    assert!(!tx_handler.is_valid_tx(&tx));
+}
+
+#[test]
+fn validate_tx_rejects_signature_by_key_not_owning_utxo() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let mallory = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    let tx = spend(&[(&utxo(1), &mallory)], &[(5, &mallory.public_key())]);
+    assert_eq!(
+        TxHandler::new(&utxo_pool).validate_tx(&tx),
+        Err(TxValidationError::BadSignature { input: TxIdx(0) })
+    );
+}
+
+#[test]
+fn validate_tx_rejects_unsigned_input() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    let tx = unsigned_tx(&alice.public_key())
+        .into_tx()
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(
+        TxHandler::new(&utxo_pool).validate_tx(&tx),
+        Err(TxValidationError::MissingSignature { input: TxIdx(0) })
+    );
+}
+
+#[test]
+fn validate_tx_rejects_genesis_input() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let tx = Tx::from_parts(
+        vec![InputTx::Genesis],
+        vec![OutputTx::new(Decimal::new(5, 0), alice.public_key())],
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(
+        TxHandler::new(&UtxoPool::new()).validate_tx(&tx),
+        Err(TxValidationError::UnexpectedGenesisInput { input: TxIdx(0) })
+    );
+}
+
+#[test]
+fn validate_tx_rejects_utxo_claimed_twice() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    let tx = spend(
+        &[(&utxo(1), &alice), (&utxo(1), &alice)],
+        &[(10, &alice.public_key())],
+    );
+    assert_eq!(
+        TxHandler::new(&utxo_pool).validate_tx(&tx),
+        Err(TxValidationError::DuplicateClaim(utxo(1)))
+    );
+}
+
+#[test]
+fn validate_tx_rejects_negative_output() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    let tx = spend(
+        &[(&utxo(1), &alice)],
+        &[(7, &alice.public_key()), (-2, &alice.public_key())],
+    );
+    assert_eq!(
+        TxHandler::new(&utxo_pool).validate_tx(&tx),
+        Err(TxValidationError::NegativeOutput(TxIdx(1)))
+    );
+}
+
+#[test]
+fn validate_tx_rejects_outputs_exceeding_inputs() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    let tx = spend(&[(&utxo(1), &alice)], &[(6, &alice.public_key())]);
+    assert_eq!(
+        TxHandler::new(&utxo_pool).validate_tx(&tx),
+        Err(TxValidationError::InsufficientInputs {
+            in_sum: Decimal::new(5, 0),
+            out_sum: Decimal::new(6, 0),
+        })
+    );
+}
+
+#[test]
+fn validate_tx_rejects_output_sum_overflow() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let address = alice.public_key();
+    let mut utxo_pool = UtxoPool::new();
+    utxo_pool
+        .add_utxo(utxo(1), OutputTx::new(Decimal::MAX, address.clone()))
+        .unwrap_or_else(|err| panic!("{}", err));
+    let mut builder = Tx::new();
+    builder
+        .add_input(TxHash([1; 32]), TxIdx(0))
+        .add_output(Decimal::MAX, address.clone())
+        .add_output(Decimal::MAX, address);
+    let tx = build(
+        builder
+            .sign_input(TxIdx(0), &alice)
+            .unwrap_or_else(|err| panic!("{}", err)),
+    );
+    assert_eq!(
+        TxHandler::new(&utxo_pool).validate_tx(&tx),
+        Err(TxValidationError::ValueOverflow)
+    );
+}
+
+#[test]
+fn handle_txs_accepts_txs_chained_within_epoch() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let bob = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    let parent = spend(&[(&utxo(1), &alice)], &[(5, &bob.public_key())]);
+    let parent_output = Utxo::new(parent.hash().clone(), TxIdx(0));
+    let child = spend(&[(&parent_output, &bob)], &[(4, &alice.public_key())]);
+    let child_output = Utxo::new(child.hash().clone(), TxIdx(0));
+    let grandchild = spend(&[(&child_output, &alice)], &[(4, &bob.public_key())]);
+
+    let mut tx_handler = TxHandler::new(&utxo_pool);
+    let accepted = tx_handler.handle_txs(&[grandchild.clone(), child.clone(), parent.clone()]);
+    assert_eq!(
+        accepted.iter().map(Tx::hash).collect::<Vec<_>>(),
+        vec![parent.hash(), child.hash(), grandchild.hash()]
+    );
+    assert_eq!(
+        tx_handler.utxo_pool().all_utxos(),
+        vec![Utxo::new(grandchild.hash().clone(), TxIdx(0))]
+    );
+    assert_eq!(
+        tx_handler.utxo_pool().balance_of(&bob.public_key()),
+        Decimal::new(4, 0)
+    );
+}
+
+#[test]
+fn handle_txs_result_does_not_depend_on_order_of_txs() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let bob = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice), (&utxo(2), 3, &bob)]);
+    // `to_bob` and `to_alice` double spend `utxo(1)`; `child` spends an output of `to_bob`
+    let to_bob = spend(&[(&utxo(1), &alice)], &[(5, &bob.public_key())]);
+    let to_alice = spend(&[(&utxo(1), &alice)], &[(4, &alice.public_key())]);
+    let child = spend(
+        &[(&Utxo::new(to_bob.hash().clone(), TxIdx(0)), &bob)],
+        &[(5, &alice.public_key())],
+    );
+    let unrelated = spend(&[(&utxo(2), &bob)], &[(3, &alice.public_key())]);
+    let (to_bob_hash, to_alice_hash) = (to_bob.hash().clone(), to_alice.hash().clone());
+    let (child_hash, unrelated_hash) = (child.hash().clone(), unrelated.hash().clone());
+    let txs = vec![to_bob, to_alice, child, unrelated];
+
+    let accept = |txs: &[Tx]| {
+        let mut tx_handler = TxHandler::new(&utxo_pool);
+        let accepted = tx_handler
+            .handle_txs(txs)
+            .iter()
+            .map(|tx| tx.hash().clone())
+            .collect::<Vec<_>>();
+        let mut utxos = tx_handler.utxo_pool().all_utxos();
+        utxos.sort_by(|lhs, rhs| (lhs.tx_hash(), lhs.tx_idx()).cmp(&(rhs.tx_hash(), rhs.tx_idx())));
+        (accepted, utxos)
+    };
+    let expected = accept(&txs);
+    // Exactly one double spend wins, under `LowestTxHash`, and `child` stands or falls with `to_bob`
+    let (winner, loser) = match to_bob_hash < to_alice_hash {
+        true => (&to_bob_hash, &to_alice_hash),
+        false => (&to_alice_hash, &to_bob_hash),
+    };
+    assert!(expected.0.contains(winner) && !expected.0.contains(loser));
+    assert_eq!(expected.0.contains(&child_hash), *winner == to_bob_hash);
+    assert!(expected.0.contains(&unrelated_hash));
+    for rotation in 0..txs.len() {
+        let mut reordered = txs.clone();
+        reordered.rotate_left(rotation);
+        assert_eq!(accept(&reordered), expected);
+        reordered.reverse();
+        assert_eq!(accept(&reordered), expected);
+    }
+}
+
+/// Return the hashes of the txs a `TxHandler` resolving double spends with `conflict_policy`
+/// accepts from `txs`, in acceptance order
+fn accepted_with<P: ConflictPolicy>(
+    conflict_policy: P,
+    utxo_pool: &UtxoPool,
+    txs: &[Tx],
+) -> Vec<TxHash> {
+    TxHandler::with_conflict_policy(utxo_pool, conflict_policy)
+        .handle_txs(txs)
+        .iter()
+        .map(|tx| tx.hash().clone())
+        .collect()
+}
+
+#[test]
+fn conflict_policies_resolve_double_spends() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let address = alice.public_key();
+    let utxo_pool = pool(&[(&utxo(1), 10, &alice)]);
+    // `cheap` pays a fee of 1 in a small tx; `rich` pays 2, but spread over a far larger tx
+    let cheap = spend(&[(&utxo(1), &alice)], &[(9, &address)]);
+    let spread = (0..20)
+        .map(|n| (i64::from(n < 8), &address))
+        .collect::<Vec<_>>();
+    let rich = spend(&[(&utxo(1), &alice)], &spread);
+    let txs = [cheap.clone(), rich.clone()];
+    let reversed = [rich.clone(), cheap.clone()];
+
+    assert_eq!(
+        accepted_with(FirstSeen, &utxo_pool, &txs),
+        vec![cheap.hash().clone()]
+    );
+    assert_eq!(
+        accepted_with(FirstSeen, &utxo_pool, &reversed),
+        vec![rich.hash().clone()]
+    );
+    for txs in &[&txs, &reversed] {
+        assert_eq!(
+            accepted_with(HighestFee, &utxo_pool, *txs),
+            vec![rich.hash().clone()]
+        );
+        assert_eq!(
+            accepted_with(HighestFeeRate, &utxo_pool, *txs),
+            vec![cheap.hash().clone()]
+        );
+        assert_eq!(
+            accepted_with(LowestTxHash, &utxo_pool, *txs),
+            vec![cheap.hash().min(rich.hash()).clone()]
+        );
+    }
+
+    let proposed = ProposedTx::new(&rich, Decimal::new(2, 0), 1);
+    assert_eq!(
+        proposed.fee_rate(),
+        Decimal::new(2, 0) / Decimal::from(rich.n_bytes())
+    );
+}
+
+#[test]
+fn epoch_report_details_accepted_and_rejected_txs() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let bob = KeyPair::new(&mut rand::thread_rng());
+    let mallory = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice), (&utxo(2), 3, &alice)]);
+    let parent = spend(&[(&utxo(1), &alice)], &[(4, &bob.public_key())]);
+    let parent_output = Utxo::new(parent.hash().clone(), TxIdx(0));
+    let child = spend(&[(&parent_output, &bob)], &[(3, &alice.public_key())]);
+    let loser = spend(&[(&utxo(1), &alice)], &[(5, &alice.public_key())]);
+    let forged = spend(&[(&utxo(2), &mallory)], &[(3, &mallory.public_key())]);
+
+    let report = TxHandler::with_conflict_policy(&utxo_pool, FirstSeen).handle_txs_with_report(&[
+        parent.clone(),
+        child.clone(),
+        loser.clone(),
+        forged.clone(),
+        parent.clone(),
+    ]);
+    assert_eq!(
+        report.accepted().iter().map(Tx::hash).collect::<Vec<_>>(),
+        vec![parent.hash(), child.hash()]
+    );
+    assert_eq!(
+        report
+            .rejected()
+            .iter()
+            .map(|rejected| (rejected.tx().witness_hash(), rejected.reason().clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                loser.witness_hash(),
+                RejectionReason::Conflict {
+                    winner: parent.hash().clone(),
+                    utxo: utxo(1),
+                }
+            ),
+            (
+                forged.witness_hash(),
+                RejectionReason::Invalid(TxValidationError::BadSignature { input: TxIdx(0) })
+            ),
+            (parent.witness_hash(), RejectionReason::Duplicate),
+        ]
+    );
+    assert_eq!(
+        report.created(),
+        &[
+            parent_output.clone(),
+            Utxo::new(child.hash().clone(), TxIdx(0))
+        ][..]
+    );
+    assert_eq!(report.spent(), &[utxo(1), parent_output][..]);
+    assert_eq!(report.total_fees(), Decimal::new(2, 0));
+}
+
+#[test]
+fn simulate_txs_leaves_pool_untouched_until_committed() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let bob = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    let tx = spend(&[(&utxo(1), &alice)], &[(5, &bob.public_key())]);
+    let mut tx_handler = TxHandler::new(&utxo_pool);
+
+    let simulation = tx_handler.simulate_txs(std::slice::from_ref(&tx));
+    assert_eq!(tx_handler.utxo_pool().all_utxos(), vec![utxo(1)]);
+    assert_eq!(tx_handler.utxo_pool().version(), utxo_pool.version());
+    assert_eq!(simulation.base_version(), utxo_pool.version());
+    assert_eq!(simulation.report().accepted().len(), 1);
+    assert_eq!(simulation.delta().removed().len(), 1);
+
+    let stale = tx_handler.simulate_txs(std::slice::from_ref(&tx));
+    let report = tx_handler
+        .commit_simulation(simulation)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(
+        report.accepted().iter().map(Tx::hash).collect::<Vec<_>>(),
+        vec![tx.hash()]
+    );
+    assert_eq!(
+        tx_handler.utxo_pool().all_utxos(),
+        vec![Utxo::new(tx.hash().clone(), TxIdx(0))]
+    );
+    assert!(matches!(
+        tx_handler.commit_simulation(stale),
+        Err(Error::StaleSimulation { .. })
+    ));
+    assert_eq!(
+        tx_handler.utxo_pool().all_utxos(),
+        vec![Utxo::new(tx.hash().clone(), TxIdx(0))]
+    );
+}
+
+#[test]
+fn commit_simulation_fails_after_pool_changes() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    let tx = spend(&[(&utxo(1), &alice)], &[(5, &alice.public_key())]);
+    let mut tx_handler = TxHandler::new(&utxo_pool);
+
+    let simulation = tx_handler.simulate_txs(&[tx]);
+    tx_handler
+        .utxo_pool_mut()
+        .add_utxo(
+            utxo(2),
+            OutputTx::new(Decimal::new(1, 0), alice.public_key()),
+        )
+        .unwrap_or_else(|err| panic!("{}", err));
+    let version = tx_handler.utxo_pool().version();
+    match tx_handler.commit_simulation(simulation) {
+        Err(Error::StaleSimulation { simulated, current }) => {
+            assert_eq!((simulated, current), (utxo_pool.version(), version));
+        }
+        other => panic!("expected a stale simulation, got {:?}", other.map(|_| ())),
+    }
+    assert!(tx_handler.utxo_pool().contains(&utxo(1)));
+}
@@ -0,0 +1,685 @@
+use super::{add_tagged, round_trip, sorted_utxos, tagged_pool, utxo};
+use crate::{
+    verify_proof, Error, KeyPair, OutputTx, Utxo, UtxoEntry, UtxoMerkleRoot, UtxoPool, UtxoProof,
+    UtxoSetCommitment,
+};
+use rust_decimal::Decimal;
+
+#[test]
+fn rollback_to_checkpoint_undoes_changes_since() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut utxo_pool = UtxoPool::new();
+    add_tagged(&mut utxo_pool, 1, &address);
+    let before = (sorted_utxos(&utxo_pool), utxo_pool.commitment());
+
+    let checkpoint = utxo_pool.checkpoint();
+    add_tagged(&mut utxo_pool, 2, &address);
+    utxo_pool.remove_utxo(&utxo(1));
+    utxo_pool
+        .insert_or_replace(utxo(2), OutputTx::new(Decimal::new(7, 0), address.clone()))
+        .unwrap_or_else(|err| panic!("{}", err));
+    utxo_pool
+        .rollback_to(checkpoint)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!((sorted_utxos(&utxo_pool), utxo_pool.commitment()), before);
+    assert_eq!(utxo_pool.balance_of(&address), Decimal::new(1, 0));
+
+    // A checkpoint stays valid after rolling back to it
+    add_tagged(&mut utxo_pool, 3, &address);
+    utxo_pool
+        .rollback_to(checkpoint)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!((sorted_utxos(&utxo_pool), utxo_pool.commitment()), before);
+}
+
+#[test]
+fn commit_keeps_changes_and_invalidates_checkpoints() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut utxo_pool = UtxoPool::new();
+    let checkpoint = utxo_pool.checkpoint();
+    add_tagged(&mut utxo_pool, 1, &address);
+    utxo_pool.commit();
+    assert_eq!(sorted_utxos(&utxo_pool), vec![utxo(1)]);
+    assert!(matches!(
+        utxo_pool.rollback_to(checkpoint),
+        Err(Error::InvalidCheckpoint)
+    ));
+
+    // A checkpoint taken after the commit at the same journal position is distinct
+    let checkpoint = utxo_pool.checkpoint();
+    add_tagged(&mut utxo_pool, 2, &address);
+    utxo_pool
+        .rollback_to(checkpoint)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(sorted_utxos(&utxo_pool), vec![utxo(1)]);
+}
+
+#[test]
+fn nested_checkpoints_roll_back_innermost_first() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut utxo_pool = UtxoPool::new();
+    let outer = utxo_pool.checkpoint();
+    add_tagged(&mut utxo_pool, 1, &address);
+    let inner = utxo_pool.checkpoint();
+    add_tagged(&mut utxo_pool, 2, &address);
+
+    utxo_pool
+        .rollback_to(inner)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(sorted_utxos(&utxo_pool), vec![utxo(1)]);
+    utxo_pool
+        .rollback_to(outer)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert!(utxo_pool.all_utxos().is_empty());
+}
+
+#[test]
+fn rolling_back_past_checkpoint_invalidates_it() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut utxo_pool = UtxoPool::new();
+    add_tagged(&mut utxo_pool, 1, &address);
+    let outer = utxo_pool.checkpoint();
+    add_tagged(&mut utxo_pool, 2, &address);
+    add_tagged(&mut utxo_pool, 3, &address);
+    let inner = utxo_pool.checkpoint();
+    add_tagged(&mut utxo_pool, 4, &address);
+    utxo_pool
+        .rollback_to(outer)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    // The journal grows back past `inner`'s position, but `inner` marks a discarded state
+    for tag in 5..=7 {
+        add_tagged(&mut utxo_pool, tag, &address);
+    }
+    let after = sorted_utxos(&utxo_pool);
+    assert!(matches!(
+        utxo_pool.rollback_to(inner),
+        Err(Error::InvalidCheckpoint)
+    ));
+    assert_eq!(sorted_utxos(&utxo_pool), after);
+    utxo_pool
+        .rollback_to(outer)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(sorted_utxos(&utxo_pool), vec![utxo(1)]);
+}
+
+#[test]
+fn adding_duplicate_utxo_keeps_existing_output() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let existing = OutputTx::new(Decimal::new(5, 0), address.clone());
+    let attempted = OutputTx::new(Decimal::new(7, 0), address.clone());
+    let mut utxo_pool = UtxoPool::new();
+    utxo_pool
+        .add_utxo(utxo(1), existing.clone())
+        .unwrap_or_else(|err| panic!("{}", err));
+    let version = utxo_pool.version();
+
+    match utxo_pool.add_utxo(utxo(1), attempted.clone()) {
+        Err(Error::AddDuplicateUtxoAttempted {
+            utxo: duplicate,
+            existing: reported_existing,
+            attempted: reported_attempted,
+        }) => assert_eq!(
+            (duplicate, reported_existing, reported_attempted),
+            (utxo(1), existing.clone(), attempted.clone())
+        ),
+        other => panic!("expected a duplicate error, got {:?}", other.map(|_| ())),
+    }
+    assert!(matches!(
+        utxo_pool.try_insert(utxo(1), attempted),
+        Err(Error::AddDuplicateUtxoAttempted { .. })
+    ));
+    assert_eq!(utxo_pool.tx_output(&utxo(1)), Some(&existing));
+    assert_eq!(utxo_pool.version(), version);
+    assert_eq!(utxo_pool.balance_of(&address), Decimal::new(5, 0));
+}
+
+#[test]
+fn insert_or_replace_returns_replaced_output() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let first = OutputTx::new(Decimal::new(5, 0), address.clone());
+    let second = OutputTx::new(Decimal::new(7, 0), address.clone());
+    let mut utxo_pool = UtxoPool::new();
+
+    assert_eq!(
+        utxo_pool
+            .insert_or_replace(utxo(1), first.clone())
+            .unwrap_or_else(|err| panic!("{}", err)),
+        None
+    );
+    assert_eq!(
+        utxo_pool
+            .insert_or_replace(utxo(1), second.clone())
+            .unwrap_or_else(|err| panic!("{}", err)),
+        Some(first)
+    );
+    assert_eq!(utxo_pool.tx_output(&utxo(1)), Some(&second));
+    assert_eq!(utxo_pool.all_utxos(), vec![utxo(1)]);
+    assert_eq!(utxo_pool.balance_of(&address), Decimal::new(7, 0));
+}
+
+#[test]
+fn utxo_entry_inspects_and_modifies_pool_in_place() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let first = OutputTx::new(Decimal::new(5, 0), address.clone());
+    let second = OutputTx::new(Decimal::new(7, 0), address.clone());
+    let mut utxo_pool = UtxoPool::new();
+
+    match utxo_pool.entry(utxo(1)) {
+        UtxoEntry::Vacant(entry) => {
+            assert_eq!(entry.key(), &utxo(1));
+            assert_eq!(
+                entry
+                    .insert(first.clone())
+                    .unwrap_or_else(|err| panic!("{}", err)),
+                &first
+            );
+        }
+        UtxoEntry::Occupied(_) => panic!("{}", "entry should be vacant"),
+    }
+    assert_eq!(
+        utxo_pool
+            .entry(utxo(1))
+            .or_insert(second.clone())
+            .unwrap_or_else(|err| panic!("{}", err)),
+        &first
+    );
+    assert_eq!(
+        utxo_pool
+            .entry(utxo(2))
+            .or_insert_with(|| second.clone())
+            .unwrap_or_else(|err| panic!("{}", err)),
+        &second
+    );
+
+    match utxo_pool.entry(utxo(1)) {
+        UtxoEntry::Occupied(mut entry) => {
+            assert_eq!((entry.key(), entry.get()), (&utxo(1), &first));
+            assert_eq!(
+                entry
+                    .replace(second.clone())
+                    .unwrap_or_else(|err| panic!("{}", err)),
+                first
+            );
+            assert_eq!(entry.into_ref(), &second);
+        }
+        UtxoEntry::Vacant(_) => panic!("{}", "entry should be occupied"),
+    }
+    assert_eq!(utxo_pool.balance_of(&address), Decimal::new(14, 0));
+
+    match utxo_pool.entry(utxo(2)) {
+        UtxoEntry::Occupied(entry) => assert_eq!(entry.remove(), second),
+        UtxoEntry::Vacant(_) => panic!("{}", "entry should be occupied"),
+    }
+    assert_eq!(utxo_pool.all_utxos(), vec![utxo(1)]);
+    assert_eq!(utxo_pool.balance_of(&address), Decimal::new(7, 0));
+}
+
+#[test]
+fn balance_queries_track_adds_removes_and_replacements() {
+    let alice = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let bob = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let sorted_utxos_for = |utxo_pool: &UtxoPool, address| {
+        let mut utxos = utxo_pool.utxos_for(address);
+        utxos.sort_by(|lhs, rhs| lhs.tx_hash().cmp(rhs.tx_hash()));
+        utxos
+    };
+    let mut utxo_pool = UtxoPool::new();
+    assert_eq!(sorted_utxos_for(&utxo_pool, &alice), Vec::<Utxo>::new());
+    assert_eq!(utxo_pool.balance_of(&alice), Decimal::new(0, 0));
+    assert_eq!(utxo_pool.total_supply(), Decimal::new(0, 0));
+
+    for (tag, value, address) in [(1, 5, &alice), (2, 3, &alice), (3, 4, &bob)] {
+        utxo_pool
+            .add_utxo(
+                utxo(tag),
+                OutputTx::new(Decimal::new(value, 0), address.clone()),
+            )
+            .unwrap_or_else(|err| panic!("{}", err));
+    }
+    assert_eq!(sorted_utxos_for(&utxo_pool, &alice), vec![utxo(1), utxo(2)]);
+    assert_eq!(sorted_utxos_for(&utxo_pool, &bob), vec![utxo(3)]);
+    assert_eq!(utxo_pool.balance_of(&alice), Decimal::new(8, 0));
+    assert_eq!(utxo_pool.total_supply(), Decimal::new(12, 0));
+
+    utxo_pool.remove_utxo(&utxo(1));
+    assert_eq!(sorted_utxos_for(&utxo_pool, &alice), vec![utxo(2)]);
+    assert_eq!(utxo_pool.balance_of(&alice), Decimal::new(3, 0));
+    assert_eq!(utxo_pool.total_supply(), Decimal::new(7, 0));
+
+    // Replacing moves the `Utxo` to its new recipient
+    utxo_pool
+        .insert_or_replace(utxo(2), OutputTx::new(Decimal::new(6, 0), bob.clone()))
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(sorted_utxos_for(&utxo_pool, &alice), Vec::<Utxo>::new());
+    assert_eq!(sorted_utxos_for(&utxo_pool, &bob), vec![utxo(2), utxo(3)]);
+    assert_eq!(utxo_pool.balance_of(&alice), Decimal::new(0, 0));
+    assert_eq!(utxo_pool.balance_of(&bob), Decimal::new(10, 0));
+    assert_eq!(utxo_pool.total_supply(), Decimal::new(10, 0));
+}
+
+#[test]
+fn supply_overflow_is_rejected_before_changing_pool() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let max = OutputTx::new(Decimal::MAX, address);
+    let mut utxo_pool = UtxoPool::new();
+    utxo_pool
+        .add_utxo(utxo(1), max.clone())
+        .unwrap_or_else(|err| panic!("{}", err));
+    let before = (sorted_utxos(&utxo_pool), utxo_pool.commitment());
+    let version = utxo_pool.version();
+
+    assert!(matches!(
+        utxo_pool.add_utxo(utxo(2), max.clone()),
+        Err(Error::SupplyOverflow(overflowing)) if overflowing == utxo(2)
+    ));
+    assert!(matches!(
+        utxo_pool.insert_or_replace(utxo(2), max.clone()),
+        Err(Error::SupplyOverflow(_))
+    ));
+    assert!(matches!(
+        utxo_pool.entry(utxo(2)).or_insert(max.clone()),
+        Err(Error::SupplyOverflow(_))
+    ));
+    let mut other = UtxoPool::new();
+    other
+        .add_utxo(utxo(2), max.clone())
+        .unwrap_or_else(|err| panic!("{}", err));
+    let delta = UtxoPool::new().diff(&other);
+    assert!(matches!(
+        utxo_pool.apply(&delta),
+        Err(Error::SupplyOverflow(_))
+    ));
+    // Replacing the `Utxo` holding the whole supply frees room for its new value
+    utxo_pool
+        .insert_or_replace(utxo(1), max.clone())
+        .unwrap_or_else(|err| panic!("{}", err));
+    utxo_pool.remove_utxo(&utxo(1));
+    utxo_pool
+        .add_utxo(utxo(1), max)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!((sorted_utxos(&utxo_pool), utxo_pool.commitment()), before);
+    assert_eq!(utxo_pool.version(), version.wrapping_add(3));
+    assert_eq!(utxo_pool.total_supply(), Decimal::MAX);
+}
+
+#[test]
+fn negative_values_do_not_let_balances_overflow() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut utxo_pool = UtxoPool::new();
+    for (tag, value) in [(1, Decimal::MAX), (2, -Decimal::MAX)] {
+        utxo_pool
+            .add_utxo(utxo(tag), OutputTx::new(value, address.clone()))
+            .unwrap_or_else(|err| panic!("{}", err));
+    }
+    assert_eq!(utxo_pool.total_supply(), Decimal::new(0, 0));
+
+    // The pool's total would fit, but removing the negative `Utxo` would then overflow it
+    assert!(matches!(
+        utxo_pool.add_utxo(utxo(3), OutputTx::new(Decimal::MAX, address.clone())),
+        Err(Error::SupplyOverflow(_))
+    ));
+    assert_eq!(utxo_pool.balance_of(&address), Decimal::new(0, 0));
+    utxo_pool.remove_utxo(&utxo(2));
+    assert_eq!(utxo_pool.balance_of(&address), Decimal::MAX);
+}
+
+#[test]
+fn commitment_does_not_depend_on_order_of_changes() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut forwards = UtxoPool::new();
+    for tag in 1..=3 {
+        add_tagged(&mut forwards, tag, &address);
+    }
+    let mut backwards = UtxoPool::new();
+    for tag in (1..=4).rev() {
+        add_tagged(&mut backwards, tag, &address);
+    }
+    backwards.remove_utxo(&utxo(4));
+    assert_eq!(forwards.commitment(), backwards.commitment());
+
+    // Removing then re-adding an entry restores the commitment; a different value does not
+    let commitment = forwards.commitment();
+    forwards.remove_utxo(&utxo(2));
+    assert_ne!(forwards.commitment(), commitment);
+    add_tagged(&mut forwards, 2, &address);
+    assert_eq!(forwards.commitment(), commitment);
+    forwards
+        .insert_or_replace(utxo(2), OutputTx::new(Decimal::new(2, 0), address.clone()))
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_ne!(forwards.commitment(), commitment);
+}
+
+#[test]
+fn commitment_round_trips_through_bytes_and_bincode() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut utxo_pool = UtxoPool::new();
+    add_tagged(&mut utxo_pool, 1, &address);
+    for commitment in [UtxoPool::new().commitment(), utxo_pool.commitment()] {
+        assert_eq!(
+            UtxoSetCommitment::from_bytes(commitment.as_bytes())
+                .unwrap_or_else(|err| panic!("{}", err)),
+            commitment
+        );
+        assert_eq!(round_trip(&commitment), commitment);
+    }
+
+    // Not the encoding of a group element
+    assert!(matches!(
+        UtxoSetCommitment::from_bytes(&[0xff; 32]),
+        Err(Error::MalformedUtxoSetCommitment)
+    ));
+    assert!(matches!(
+        UtxoSetCommitment::from_bytes(&[0; 31]),
+        Err(Error::MalformedUtxoSetCommitment)
+    ));
+    assert!(bincode::deserialize::<UtxoSetCommitment>(&[0xff; 32]).is_err());
+}
+
+#[test]
+fn merkle_proofs_prove_inclusion_and_exclusion() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let utxo_pool = tagged_pool(1..=32, &address);
+    let root = utxo_pool.merkle_root();
+    for tag in 1..=32 {
+        let proof = utxo_pool
+            .prove_inclusion(&utxo(tag))
+            .unwrap_or_else(|| panic!("no inclusion proof for {}", tag));
+        assert!(verify_proof(&root, &proof));
+        assert_eq!(utxo_pool.prove_exclusion(&utxo(tag)), None);
+    }
+    for tag in 33..=96 {
+        let proof = utxo_pool
+            .prove_exclusion(&utxo(tag))
+            .unwrap_or_else(|| panic!("no exclusion proof for {}", tag));
+        assert!(verify_proof(&root, &proof));
+        assert_eq!(utxo_pool.prove_inclusion(&utxo(tag)), None);
+    }
+
+    // The path to a missing `Utxo` ends at an empty subtree, or at the leaf of another `Utxo`
+    let empty = UtxoPool::new();
+    assert!(matches!(
+        empty.prove_exclusion(&utxo(1)),
+        Some(UtxoProof::Exclusion {
+            neighbour: None,
+            ..
+        })
+    ));
+    let single = tagged_pool(1..=1, &address);
+    let proof = single
+        .prove_exclusion(&utxo(2))
+        .unwrap_or_else(|| panic!("{}", "no exclusion proof"));
+    assert!(matches!(
+        proof,
+        UtxoProof::Exclusion {
+            neighbour: Some(_),
+            ..
+        }
+    ));
+    assert!(verify_proof(&single.merkle_root(), &proof));
+}
+
+#[test]
+fn merkle_root_depends_only_on_pool_contents() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let utxo_pool = tagged_pool(1..=16, &address);
+    let mut rebuilt = tagged_pool((1..=24).rev(), &address);
+    for tag in 17..=24 {
+        rebuilt.remove_utxo(&utxo(tag));
+    }
+    assert_eq!(rebuilt.merkle_root(), utxo_pool.merkle_root());
+
+    rebuilt.remove_utxo(&utxo(1));
+    assert_ne!(rebuilt.merkle_root(), utxo_pool.merkle_root());
+    for tag in 2..=16 {
+        rebuilt.remove_utxo(&utxo(tag));
+    }
+    assert_eq!(rebuilt.merkle_root(), UtxoPool::new().merkle_root());
+}
+
+#[test]
+fn forged_merkle_proofs_are_rejected() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut utxo_pool = tagged_pool(1..=8, &address);
+    let root = utxo_pool.merkle_root();
+    let inclusion = |utxo_pool: &UtxoPool, tag| {
+        utxo_pool
+            .prove_inclusion(&utxo(tag))
+            .unwrap_or_else(|| panic!("no inclusion proof for {}", tag))
+    };
+    let (tx_out, siblings) = match inclusion(&utxo_pool, 1) {
+        UtxoProof::Inclusion {
+            tx_out, siblings, ..
+        } => (tx_out, siblings),
+        UtxoProof::Exclusion { .. } => panic!("{}", "expected an inclusion proof"),
+    };
+
+    let forge = |utxo, tx_out, siblings| UtxoProof::Inclusion {
+        utxo,
+        tx_out,
+        siblings,
+    };
+    // Another value, another `Utxo`, a tampered sibling or too many siblings
+    let inflated = OutputTx::new(Decimal::new(1000, 0), address.clone());
+    assert!(!verify_proof(
+        &root,
+        &forge(utxo(1), inflated, siblings.clone())
+    ));
+    assert!(!verify_proof(
+        &root,
+        &forge(utxo(2), tx_out.clone(), siblings.clone())
+    ));
+    let mut tampered = siblings.clone();
+    if let Some(byte) = tampered.first_mut().and_then(|sibling| sibling.first_mut()) {
+        *byte ^= 1;
+    }
+    assert!(!verify_proof(
+        &root,
+        &forge(utxo(1), tx_out.clone(), tampered)
+    ));
+    let mut padded = siblings;
+    padded.resize(257, [0; 32]);
+    assert!(!verify_proof(&root, &forge(utxo(1), tx_out, padded)));
+
+    // A `Utxo`'s own leaf cannot stand in as the neighbour proving it absent
+    let single = tagged_pool(1..=1, &address);
+    let neighbour = match single.prove_exclusion(&utxo(2)) {
+        Some(UtxoProof::Exclusion { neighbour, .. }) => neighbour,
+        other => panic!("expected an exclusion proof, got {:?}", other),
+    };
+    assert!(!verify_proof(
+        &single.merkle_root(),
+        &UtxoProof::Exclusion {
+            utxo: utxo(1),
+            neighbour,
+            siblings: Vec::new(),
+        }
+    ));
+
+    // Proofs are only good against the root they were made for
+    let stale = inclusion(&utxo_pool, 1);
+    utxo_pool.remove_utxo(&utxo(1));
+    assert!(!verify_proof(&utxo_pool.merkle_root(), &stale));
+    let exclusion = utxo_pool
+        .prove_exclusion(&utxo(1))
+        .unwrap_or_else(|| panic!("{}", "no exclusion proof"));
+    assert!(verify_proof(&utxo_pool.merkle_root(), &exclusion));
+    assert!(!verify_proof(&root, &exclusion));
+}
+
+#[test]
+fn merkle_roots_and_proofs_round_trip_through_bytes() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let utxo_pool = tagged_pool(1..=4, &address);
+    let root = utxo_pool.merkle_root();
+    assert_eq!(
+        UtxoMerkleRoot::from_bytes(root.as_bytes()).unwrap_or_else(|err| panic!("{}", err)),
+        root
+    );
+    assert!(matches!(
+        UtxoMerkleRoot::from_bytes(&[0; 31]),
+        Err(Error::UtxoMerkleRootLength(31))
+    ));
+    assert_eq!(round_trip(&root), root);
+
+    for proof in [
+        utxo_pool.prove_inclusion(&utxo(1)),
+        utxo_pool.prove_exclusion(&utxo(5)),
+    ]
+    .iter()
+    .flatten()
+    {
+        let decoded =
+            UtxoProof::from_bytes(&proof.to_bytes()).unwrap_or_else(|err| panic!("{}", err));
+        assert_eq!(&decoded, proof);
+        assert!(verify_proof(&root, &decoded));
+        assert_eq!(&round_trip(proof), proof);
+
+        let mut trailing = proof.to_bytes();
+        trailing.push(0);
+        assert!(matches!(
+            UtxoProof::from_bytes(&trailing),
+            Err(Error::MalformedUtxoProof)
+        ));
+        let truncated = proof.to_bytes();
+        assert!(matches!(
+            UtxoProof::from_bytes(
+                truncated
+                    .get(..truncated.len().saturating_sub(1))
+                    .unwrap_or(&[])
+            ),
+            Err(Error::MalformedUtxoProof)
+        ));
+    }
+}
+
+#[test]
+fn applying_diff_turns_pool_into_other() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let from = tagged_pool(1..=4, &address);
+    let mut to = tagged_pool(3..=6, &address);
+    to.insert_or_replace(utxo(3), OutputTx::new(Decimal::new(30, 0), address))
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let delta = from.diff(&to);
+    // `utxo(3)` changed value, so it is both removed and added
+    let utxos = |entries: &[(Utxo, OutputTx)]| {
+        let mut utxos = entries
+            .iter()
+            .map(|(utxo, _)| utxo.clone())
+            .collect::<Vec<_>>();
+        utxos.sort_by(|lhs, rhs| lhs.tx_hash().cmp(rhs.tx_hash()));
+        utxos
+    };
+    assert_eq!(utxos(delta.removed()), vec![utxo(1), utxo(2), utxo(3)]);
+    assert_eq!(utxos(delta.added()), vec![utxo(3), utxo(5), utxo(6)]);
+
+    let mut patched = from;
+    patched
+        .apply(&delta)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert!(patched.diff(&to).is_empty());
+    assert_eq!(patched.commitment(), to.commitment());
+    assert_eq!(patched.merkle_root(), to.merkle_root());
+    assert_eq!(patched.total_supply(), to.total_supply());
+    assert!(to.diff(&to).is_empty());
+}
+
+#[test]
+fn applying_delta_fails_unless_preconditions_hold() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let from = tagged_pool(1..=2, &address);
+    let to = tagged_pool(2..=3, &address);
+    let delta = from.diff(&to);
+    let assert_rejected = |utxo_pool: &mut UtxoPool, expected: fn(&Error) -> bool| {
+        let before = (sorted_utxos(utxo_pool), utxo_pool.commitment());
+        let version = utxo_pool.version();
+        match utxo_pool.apply(&delta) {
+            Err(err) => assert!(expected(&err), "unexpected error: {}", err),
+            Ok(_) => panic!("{}", "delta should not apply"),
+        }
+        assert_eq!((sorted_utxos(utxo_pool), utxo_pool.commitment()), before);
+        assert_eq!(utxo_pool.version(), version);
+    };
+
+    // A removed `Utxo` which is gone, or maps to another tx output
+    let mut spent = from.clone();
+    spent.remove_utxo(&utxo(1));
+    assert_rejected(
+        &mut spent,
+        |err| matches!(err, Error::DeltaRemovesMissingUtxo(missing) if *missing == utxo(1)),
+    );
+    let mut changed = from.clone();
+    changed
+        .insert_or_replace(utxo(1), OutputTx::new(Decimal::new(9, 0), address.clone()))
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_rejected(&mut changed, |err| {
+        matches!(err, Error::DeltaRemovesMissingUtxo(_))
+    });
+
+    // A delta applies only once, as what it removes is then gone
+    let mut applied = from.clone();
+    applied
+        .apply(&delta)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_rejected(&mut applied, |err| {
+        matches!(err, Error::DeltaRemovesMissingUtxo(_))
+    });
+    // An added `Utxo` which is already there
+    let mut added = from;
+    add_tagged(&mut added, 3, &address);
+    assert_rejected(
+        &mut added,
+        |err| matches!(err, Error::DeltaAddsExistingUtxo(existing) if *existing == utxo(3)),
+    );
+}
+
+#[test]
+fn cloned_pools_change_independently() {
+    let alice = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let bob = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut original = tagged_pool(1..=8, &alice);
+    let snapshot = |utxo_pool: &UtxoPool| {
+        (
+            sorted_utxos(utxo_pool),
+            utxo_pool.commitment(),
+            utxo_pool.merkle_root(),
+            utxo_pool.balance_of(&alice),
+            utxo_pool.total_supply(),
+        )
+    };
+    let before = snapshot(&original);
+
+    // Changes to the clone leave the original untouched
+    let mut clone = original.clone();
+    clone.remove_utxo(&utxo(1));
+    clone
+        .insert_or_replace(utxo(2), OutputTx::new(Decimal::new(20, 0), bob.clone()))
+        .unwrap_or_else(|err| panic!("{}", err));
+    add_tagged(&mut clone, 9, &alice);
+    assert_eq!(snapshot(&original), before);
+    assert_eq!(original.utxos_for(&bob), Vec::<Utxo>::new());
+    let cloned = snapshot(&clone);
+    assert_ne!(cloned, before);
+
+    // And changes to the original leave the clone untouched
+    original.remove_utxo(&utxo(3));
+    add_tagged(&mut original, 10, &bob);
+    assert_eq!(snapshot(&clone), cloned);
+    assert!(!clone.contains(&utxo(10)));
+    assert!(clone.contains(&utxo(3)));
+
+    // A clone taken inside a checkpoint rolls back separately from the original
+    let checkpoint = original.checkpoint();
+    original.remove_utxo(&utxo(4));
+    let mut nested = original.clone();
+    original
+        .rollback_to(checkpoint)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert!(original.contains(&utxo(4)));
+    assert!(!nested.contains(&utxo(4)));
+    nested
+        .rollback_to(checkpoint)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(snapshot(&nested), snapshot(&original));
+}
@@ -0,0 +1,216 @@
+use super::{add_tagged, pool, sorted_utxos, spend, utxo};
+use crate::{
+    consts, Error, FileUtxoStore, KeyPair, LowestTxHash, TxHandler, TxIdx, Utxo, UtxoPool,
+    UtxoStore,
+};
+use rust_decimal::Decimal;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Return a fresh directory for a `FileUtxoStore`, unique to this call
+fn store_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "scrooge_coin-{}-{:016x}",
+        name,
+        rand::random::<u64>()
+    ))
+}
+
+fn open_store(dir: &Path) -> FileUtxoStore {
+    FileUtxoStore::open(dir).unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn sorted_store_utxos(utxo_store: &impl UtxoStore) -> Vec<Utxo> {
+    let mut utxos = utxo_store.all_utxos();
+    utxos.sort_by(|lhs, rhs| lhs.tx_hash().cmp(rhs.tx_hash()));
+    utxos
+}
+
+/// Opens a store in a fresh directory holding `utxo_pool`'s `Utxo`s
+fn seeded_store(name: &str, utxo_pool: &UtxoPool) -> FileUtxoStore {
+    let mut utxo_store = open_store(&store_dir(name));
+    utxo_store
+        .apply_delta(&UtxoPool::new().diff(utxo_pool))
+        .unwrap_or_else(|err| panic!("{}", err));
+    utxo_store
+}
+
+#[test]
+fn file_utxo_store_opens_empty_and_survives_reopen() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let bob = KeyPair::new(&mut rand::thread_rng());
+    let utxo_pool = pool(&[(&utxo(1), 5, &alice)]);
+    let dir = store_dir("reopen");
+    assert!(open_store(&dir).all_utxos().is_empty());
+
+    let mut utxo_store = open_store(&dir);
+    utxo_store
+        .apply_delta(&UtxoPool::new().diff(&utxo_pool))
+        .unwrap_or_else(|err| panic!("{}", err));
+    let parent = spend(&[(&utxo(1), &alice)], &[(5, &bob.public_key())]);
+    let child = spend(
+        &[(&Utxo::new(parent.hash().clone(), TxIdx(0)), &bob)],
+        &[(4, &alice.public_key())],
+    );
+    let mut tx_handler = TxHandler::with_store(utxo_store, LowestTxHash);
+    assert_eq!(
+        tx_handler
+            .try_handle_txs(&[child.clone(), parent])
+            .unwrap_or_else(|err| panic!("{}", err))
+            .len(),
+        2
+    );
+    let expected = sorted_store_utxos(tx_handler.utxo_pool());
+    assert_eq!(expected, vec![Utxo::new(child.hash().clone(), TxIdx(0))]);
+    drop(tx_handler);
+
+    // Replaying the log recovers every change, and does so again on a second reopen
+    let reopened = open_store(&dir);
+    assert_eq!(sorted_store_utxos(&reopened), expected);
+    assert_eq!(
+        reopened.utxo_pool().balance_of(&alice.public_key()),
+        Decimal::new(4, 0)
+    );
+    drop(reopened);
+    assert_eq!(sorted_store_utxos(&open_store(&dir)), expected);
+    fs::remove_dir_all(&dir).unwrap_or_else(|err| panic!("{}", err));
+}
+
+#[test]
+fn file_utxo_store_discards_torn_log_tail() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let utxo_store = seeded_store("torn", &pool(&[(&utxo(1), 5, &alice)]));
+    let dir = utxo_store.dir().to_path_buf();
+    drop(utxo_store);
+    let log_path = dir.join(consts::FILE_UTXO_STORE_LOG_FILE_NAME);
+    let log_len = fs::metadata(&log_path)
+        .unwrap_or_else(|err| panic!("{}", err))
+        .len();
+
+    // A record cut short by a crash mid-append
+    let mut log = OpenOptions::new()
+        .append(true)
+        .open(&log_path)
+        .unwrap_or_else(|err| panic!("{}", err));
+    log.write_all(&[42, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3])
+        .unwrap_or_else(|err| panic!("{}", err));
+    drop(log);
+
+    let mut utxo_store = open_store(&dir);
+    assert_eq!(sorted_store_utxos(&utxo_store), vec![utxo(1)]);
+    assert_eq!(
+        fs::metadata(&log_path)
+            .unwrap_or_else(|err| panic!("{}", err))
+            .len(),
+        log_len
+    );
+
+    // Changes made after recovery follow the last complete record and survive a reopen
+    let mut grown = utxo_store.utxo_pool().clone();
+    add_tagged(&mut grown, 2, &alice.public_key());
+    utxo_store
+        .apply_delta(&utxo_store.utxo_pool().diff(&grown))
+        .unwrap_or_else(|err| panic!("{}", err));
+    drop(utxo_store);
+    assert_eq!(
+        sorted_store_utxos(&open_store(&dir)),
+        vec![utxo(1), utxo(2)]
+    );
+    fs::remove_dir_all(&dir).unwrap_or_else(|err| panic!("{}", err));
+}
+
+#[test]
+fn file_utxo_store_rejects_corrupt_log_record_without_truncating() {
+    let alice = KeyPair::new(&mut rand::thread_rng());
+    let utxo_store = seeded_store("corrupt_log", &pool(&[(&utxo(1), 5, &alice)]));
+    let dir = utxo_store.dir().to_path_buf();
+    drop(utxo_store);
+    let log_path = dir.join(consts::FILE_UTXO_STORE_LOG_FILE_NAME);
+    let mut bytes = fs::read(&log_path).unwrap_or_else(|err| panic!("{}", err));
+
+    // A complete record whose body no longer matches its checksum is damage, not a torn tail
+    if let Some(last) = bytes.last_mut() {
+        *last ^= 0xff;
+    }
+    fs::write(&log_path, &bytes).unwrap_or_else(|err| panic!("{}", err));
+    assert!(matches!(
+        FileUtxoStore::open(&dir),
+        Err(Error::CorruptUtxoStore(_))
+    ));
+    assert_eq!(
+        fs::read(&log_path).unwrap_or_else(|err| panic!("{}", err)),
+        bytes
+    );
+    fs::remove_dir_all(&dir).unwrap_or_else(|err| panic!("{}", err));
+}
+
+#[test]
+fn file_utxo_store_compacts_log_into_snapshot() {
+    let address = KeyPair::new(&mut rand::thread_rng()).public_key();
+    let mut utxo_store = open_store(&store_dir("compact"));
+    let dir = utxo_store.dir().to_path_buf();
+    utxo_store.set_compaction_interval(2);
+    let mut utxo_pool = UtxoPool::new();
+    for tag in 1..=5 {
+        let before = utxo_pool.clone();
+        add_tagged(&mut utxo_pool, tag, &address);
+        utxo_store
+            .apply_delta(&before.diff(&utxo_pool))
+            .unwrap_or_else(|err| panic!("{}", err));
+    }
+    utxo_pool.remove_utxo(&utxo(3));
+    utxo_store
+        .apply_delta(&utxo_store.utxo_pool().diff(&utxo_pool))
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert!(dir
+        .join(consts::FILE_UTXO_STORE_SNAPSHOT_FILE_NAME)
+        .exists());
+    drop(utxo_store);
+
+    // An interrupted compaction's temporary snapshot is discarded on open
+    fs::write(
+        dir.join(consts::FILE_UTXO_STORE_SNAPSHOT_TMP_FILE_NAME),
+        b"partial",
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+    let mut utxo_store = open_store(&dir);
+    assert!(!dir
+        .join(consts::FILE_UTXO_STORE_SNAPSHOT_TMP_FILE_NAME)
+        .exists());
+    assert_eq!(sorted_store_utxos(&utxo_store), sorted_utxos(&utxo_pool));
+
+    utxo_store.compact().unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(
+        fs::metadata(dir.join(consts::FILE_UTXO_STORE_LOG_FILE_NAME))
+            .unwrap_or_else(|err| panic!("{}", err))
+            .len(),
+        0
+    );
+    drop(utxo_store);
+    assert_eq!(
+        sorted_store_utxos(&open_store(&dir)),
+        sorted_utxos(&utxo_pool)
+    );
+    fs::remove_dir_all(&dir).unwrap_or_else(|err| panic!("{}", err));
+}
+
+#[test]
+fn file_utxo_store_rejects_corrupt_snapshot() {
+    let utxo_store = open_store(&store_dir("corrupt"));
+    let dir = utxo_store.dir().to_path_buf();
+    drop(utxo_store);
+    fs::write(
+        dir.join(consts::FILE_UTXO_STORE_SNAPSHOT_FILE_NAME),
+        b"not a snapshot",
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+
+    assert!(matches!(
+        FileUtxoStore::open(&dir),
+        Err(Error::CorruptUtxoStore(_))
+    ));
+    fs::remove_dir_all(&dir).unwrap_or_else(|err| panic!("{}", err));
+}
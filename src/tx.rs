@@ -1,8 +1,11 @@
 mod address;
+mod canonical;
 mod input_tx;
 mod non_empty_ext;
 mod operation;
 mod output_tx;
+mod partially_signed_tx;
+mod sighash;
 mod tx_builder;
 mod tx_hash;
 mod tx_idx;
@@ -12,13 +15,28 @@ pub use input_tx::InputTx;
 use nonempty::NonEmpty as NonEmptyVec;
 pub use operation::Operation;
 pub use output_tx::OutputTx;
-pub use tx_builder::TxBuilder;
+pub use partially_signed_tx::PartiallySignedTx;
+pub use sighash::{SigHash, SigHashOutputs};
+pub use tx_builder::{FullySigned, Modifiable, PartiallySigned, SigningState, TxBuilder, Unsigned};
 pub use tx_hash::TxHash;
 pub use tx_idx::TxIdx;
 
+use crate::{
+    consts::msg, error::TxValidationError, utxo_pool::UtxoView, Error, Result, Utxo, UtxoPool,
+};
+use non_empty_ext::NonEmptyExt;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::HashSet, convert::TryFrom};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(into = "TxRepr", try_from = "TxRepr")]
 pub struct Tx {
-    /// Hash of the tx, its unique ID
+    /// Hash of the tx without its signatures, its unique ID
     hash: TxHash,
+    /// Hash of the tx including its signatures
+    witness_hash: TxHash,
     inputs: NonEmptyVec<InputTx>,
     outputs: NonEmptyVec<OutputTx>,
 }
@@ -30,8 +48,282 @@ impl Tx {
         TxBuilder::new()
     }
 
+    /// Return the tx's ID.  It covers the tx's inputs and outputs but not its signatures, so
+    /// re-signing a tx (or altering a signature's encoding) cannot change the ID that `Utxo`s refer
+    /// to.
     #[must_use]
     pub const fn hash(&self) -> &TxHash {
         &self.hash
     }
+
+    /// Return the hash of the tx's full canonical encoding, signatures included.  Distinguishes
+    /// differently-signed copies of a tx that share a `hash()`.
+    #[must_use]
+    pub const fn witness_hash(&self) -> &TxHash {
+        &self.witness_hash
+    }
+
+    /// Return the tx in its canonical binary encoding, which `witness_hash()` is computed over;
+    /// `hash()` is computed over the same encoding with each signed input encoded as unsigned.
+    /// Integers are little-endian and every variable-length field is prefixed with its length as a
+    /// `u64`:
+    ///
+    /// ```text
+    /// tx        = magic:"SCRG" version:u8(1) inputs outputs
+    /// inputs    = count:u64 input*
+    /// input     = 0x00                                                        ; genesis
+    ///           | 0x01 prev_tx_hash:bytes output_idx:u64                      ; unsigned
+    ///           | 0x02 prev_tx_hash:bytes output_idx:u64 sig:bytes sighash:u8 ; signed
+    /// outputs   = count:u64 output*
+    /// output    = value address:bytes
+    /// value     = negative:u8 scale:u8 mantissa:u96
+    /// bytes     = len:u64 u8*
+    /// ```
+    ///
+    /// Values are normalized (no trailing zeros after the decimal point) and zero is never
+    /// negative, so each tx has exactly one encoding.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        canonical::encode(self.inputs.iter(), self.outputs.iter())
+    }
+
+    /// Decodes a tx from its canonical binary encoding (see `encode()`).  Fails if `bytes` is not
+    /// exactly one canonically encoded tx.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let (inputs, outputs) = canonical::decode(bytes)?;
+        Self::from_parts(inputs, outputs)
+    }
+
+    /// Assembles a tx from `inputs` and `outputs` as they stand, signed or not
+    pub(crate) fn from_parts(inputs: Vec<InputTx>, outputs: Vec<OutputTx>) -> Result<Self> {
+        Ok(Self {
+            hash: hash_tx(&inputs, &outputs),
+            witness_hash: witness_hash_tx(&inputs, &outputs),
+            inputs: <NonEmptyVec<_> as NonEmptyExt<_>>::try_from(inputs)
+                .ok_or(Error::CannotBuildTxWithoutInputTxs)?,
+            outputs: <NonEmptyVec<_> as NonEmptyExt<_>>::try_from(outputs)
+                .ok_or(Error::CannotBuildTxWithoutOutputTxs)?,
+        })
+    }
+
+    /// Return the inputs of the tx, in order
+    pub fn inputs(&self) -> impl Iterator<Item = &InputTx> + '_ {
+        self.inputs.iter()
+    }
+
+    /// Return the outputs of the tx, in order
+    pub fn outputs(&self) -> impl Iterator<Item = &OutputTx> + '_ {
+        self.outputs.iter()
+    }
+
+    /// Return the input at `idx` or `None` if there is no such input
+    #[must_use]
+    pub fn input_tx(&self, idx: TxIdx) -> Option<&InputTx> {
+        usize::try_from(idx)
+            .ok()
+            .and_then(|position| self.inputs.get(position))
+    }
+
+    /// Return the output at `idx` or `None` if there is no such output
+    #[must_use]
+    pub fn output_tx(&self, idx: TxIdx) -> Option<&OutputTx> {
+        usize::try_from(idx)
+            .ok()
+            .and_then(|position| self.outputs.get(position))
+    }
+
+    #[must_use]
+    pub fn n_input_txs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    #[must_use]
+    pub fn n_output_txs(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Return the index of each input of the tx, in order
+    pub fn input_idxs(&self) -> impl Iterator<Item = TxIdx> {
+        TxIdx::range(self.inputs.len())
+    }
+
+    /// Return the index of each output of the tx, in order
+    pub fn output_idxs(&self) -> impl Iterator<Item = TxIdx> {
+        TxIdx::range(self.outputs.len())
+    }
+
+    /// Return the sum of the tx's output values.  Fails if an output value is negative or the sum
+    /// overflows.
+    pub fn total_output(&self) -> Result<Decimal, TxValidationError> {
+        self.outputs.iter().enumerate().try_fold(
+            Decimal::new(0, 0),
+            |out_sum, (position, output)| {
+                if output.value() < Decimal::new(0, 0) {
+                    Err(TxValidationError::NegativeOutput(TxIdx::from_position(
+                        position,
+                    )))?;
+                }
+                out_sum
+                    .checked_add(output.value())
+                    .ok_or(TxValidationError::ValueOverflow)
+            },
+        )
+    }
+
+    /// Return the fee the tx pays against `utxo_pool`: the sum of the values of the outputs its
+    /// inputs spend less `total_output()`.  Checks every rule of `TxHandler::is_valid_tx()` except
+    /// those on signatures (see `verify_signatures()`).
+    pub fn fee(&self, utxo_pool: &UtxoPool) -> Result<Decimal, TxValidationError> {
+        self.fee_against(utxo_pool)
+    }
+
+    /// Checks that every input of the tx claims a `Utxo` in `utxo_pool` and carries a signature,
+    /// made by the owner of that `Utxo`, over the parts of the tx its `SigHash` selects
+    pub fn verify_signatures(&self, utxo_pool: &UtxoPool) -> Result<(), TxValidationError> {
+        self.verify_signatures_against(utxo_pool)
+    }
+
+    /// As `fee()`, against any collection of UTXOs
+    pub(crate) fn fee_against(&self, utxos: &impl UtxoView) -> Result<Decimal, TxValidationError> {
+        let mut claimed = HashSet::new();
+        let in_sum = self.inputs.iter().enumerate().try_fold(
+            Decimal::new(0, 0),
+            |in_sum, (position, input)| {
+                let (utxo, output) = claimed_output(utxos, position, input)?;
+                if !claimed.insert(utxo.clone()) {
+                    Err(TxValidationError::DuplicateClaim(utxo))?;
+                }
+                in_sum
+                    .checked_add(output.value())
+                    .ok_or(TxValidationError::ValueOverflow)
+            },
+        )?;
+        let out_sum = self.total_output()?;
+
+        match in_sum >= out_sum {
+            true => Ok(in_sum
+                .checked_sub(out_sum)
+                .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL))),
+            false => Err(TxValidationError::InsufficientInputs { in_sum, out_sum }),
+        }
+    }
+
+    /// As `verify_signatures()`, against any collection of UTXOs
+    pub(crate) fn verify_signatures_against(
+        &self,
+        utxos: &impl UtxoView,
+    ) -> Result<(), TxValidationError> {
+        self.inputs
+            .iter()
+            .enumerate()
+            .try_for_each(|(position, input)| {
+                let input_idx = TxIdx::from_position(position);
+                let (_, output) = claimed_output(utxos, position, input)?;
+                let (signature, sighash) = input
+                    .signature()
+                    .zip(input.sighash())
+                    .ok_or(TxValidationError::MissingSignature { input: input_idx })?;
+                let message = self
+                    .signing_message(position, sighash)
+                    .ok_or(TxValidationError::SigHashSingleWithoutOutput { input: input_idx })?;
+                output
+                    .address()
+                    .verify(&message, signature)
+                    .map_err(|_| TxValidationError::BadSignature { input: input_idx })
+            })
+    }
+
+    /// Return the `Utxo`s claimed by the inputs of the tx
+    pub fn spent_utxos(&self) -> impl Iterator<Item = Utxo> + '_ {
+        self.inputs.iter().filter_map(InputTx::utxo)
+    }
+
+    /// Return the `Utxo`s the outputs of the tx add to the `UtxoPool` once it is accepted
+    pub fn created_utxos(&self) -> impl Iterator<Item = Utxo> + '_ {
+        self.output_idxs()
+            .map(move |idx| Utxo::new(self.hash.clone(), idx))
+    }
+
+    /// Return the size of the tx's canonical encoding in bytes
+    pub(crate) fn n_bytes(&self) -> usize {
+        self.encode().len()
+    }
+
+    /// Return the message the input at `position` signs under `sighash`, or `None` if there is
+    /// no such input or `sighash` selects an output the tx does not have
+    pub(crate) fn signing_message(&self, position: usize, sighash: SigHash) -> Option<Vec<u8>> {
+        sighash::signing_message(
+            &self.inputs.iter().collect::<Vec<_>>(),
+            &self.outputs.iter().collect::<Vec<_>>(),
+            position,
+            sighash,
+        )
+    }
+}
+
+/// Serialized form of a `Tx`.  Deserialization rebuilds the tx from its inputs and outputs and
+/// rejects it if the recorded hash differs from the rebuilt one.
+#[derive(Deserialize, Serialize)]
+struct TxRepr {
+    hash: TxHash,
+    inputs: Vec<InputTx>,
+    outputs: Vec<OutputTx>,
+}
+
+impl From<Tx> for TxRepr {
+    fn from(tx: Tx) -> Self {
+        Self {
+            hash: tx.hash,
+            inputs: tx.inputs.iter().cloned().collect(),
+            outputs: tx.outputs.iter().cloned().collect(),
+        }
+    }
+}
+
+impl TryFrom<TxRepr> for Tx {
+    type Error = Error;
+
+    fn try_from(repr: TxRepr) -> Result<Self> {
+        let tx = Self::from_parts(repr.inputs, repr.outputs)?;
+        match tx.hash == repr.hash {
+            true => Ok(tx),
+            false => Err(Error::TxHashMismatch {
+                recorded: repr.hash,
+                computed: tx.hash,
+            }),
+        }
+    }
+}
+
+/// Return the `Utxo` claimed by `input`, at `position` in its tx, and the output it maps to in
+/// `utxos`
+fn claimed_output<'a>(
+    utxos: &'a impl UtxoView,
+    position: usize,
+    input: &InputTx,
+) -> Result<(Utxo, &'a OutputTx), TxValidationError> {
+    let utxo = input
+        .utxo()
+        .ok_or_else(|| TxValidationError::UnexpectedGenesisInput {
+            input: TxIdx::from_position(position),
+        })?;
+    let output = utxos
+        .tx_output(&utxo)
+        .ok_or_else(|| TxValidationError::MissingUtxo(utxo.clone()))?;
+    Ok((utxo, output))
+}
+
+/// Hashes the tx's canonical encoding without signatures, so the tx's ID neither depends on how
+/// the crate lays out its types in memory nor changes when the tx is re-signed
+fn hash_tx(inputs: &[InputTx], outputs: &[OutputTx]) -> TxHash {
+    let mut hasher = Sha256::new();
+    hasher.update(canonical::encode_without_signatures(inputs, outputs));
+    TxHash::try_from(&*hasher.finalize()).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL))
+}
+
+/// Hashes the tx's full canonical encoding, signatures included
+fn witness_hash_tx(inputs: &[InputTx], outputs: &[OutputTx]) -> TxHash {
+    let mut hasher = Sha256::new();
+    hasher.update(canonical::encode(inputs, outputs));
+    TxHash::try_from(&*hasher.finalize()).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL))
 }
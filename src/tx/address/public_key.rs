@@ -1,5 +1,10 @@
 use crate::{consts, tx::Address, Error, Result, Signature};
-use serde::{Serialize, Serializer};
+use ed25519_dalek::{SignatureError, Verifier};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use sha2::Sha512;
 use std::{
     fmt::{Debug, Display, Formatter, Result as FmtResult},
     hash::{Hash, Hasher},
@@ -9,12 +14,26 @@ use std::{
 pub struct PublicKey(ed25519_dalek::PublicKey);
 
 impl PublicKey {
-    pub fn verify(
+    /// Verifies `signature` on `message` using Ed25519 (RFC 8032, section 5.1.7)
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> Result<(), SignatureError> {
+        self.0.verify(message, &signature.0)
+    }
+
+    /// Verifies an Ed25519ph `signature` (RFC 8032, section 5.1) on the message fed into
+    /// `prehashed_message`, made under the optional `context` of at most 255 bytes
+    pub fn verify_prehashed(
         &self,
-        message: &[u8],
+        prehashed_message: Sha512,
+        context: Option<&[u8]>,
         signature: &Signature,
-    ) -> Result<(), ed25519_dalek::errors::SignatureError> {
-        self.0.verify::<sha2::Sha256>(message, &signature.0)
+    ) -> Result<()> {
+        let context_len = context.map_or(0, <[u8]>::len);
+        if context_len > consts::ED25519PH_MAX_CONTEXT_LEN {
+            Err(Error::Ed25519phContextTooLong(context_len))?;
+        }
+        Ok(self
+            .0
+            .verify_prehashed(prehashed_message, context, &signature.0)?)
     }
 }
 
@@ -32,13 +51,13 @@ impl Address for PublicKey {
 
 impl Display for PublicKey {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        self.0.fmt(f)
+        Debug::fmt(&self.0, f)
     }
 }
 
 impl Hash for PublicKey {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        Hash::hash_slice(self.0.as_bytes(), state)
+        Hash::hash_slice(self.0.as_bytes(), state);
     }
 }
 
@@ -59,3 +78,34 @@ impl Serialize for PublicKey {
         )
     }
 }
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as Deserializer<'de>>::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(
+            consts::ED25519_DALEK_PUBLIC_KEY_TYPE_NAME,
+            PublicKeyVisitor,
+        )
+    }
+}
+
+/// Accepts only the bytes of a valid ed25519 public key
+struct PublicKeyVisitor;
+
+impl<'de> Visitor<'de> for PublicKeyVisitor {
+    type Value = PublicKey;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(consts::ED25519_DALEK_PUBLIC_KEY_TYPE_NAME)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = <[u8; ed25519_dalek::PUBLIC_KEY_LENGTH]>::deserialize(deserializer)?;
+        PublicKey::from_slice(bytes).map_err(de::Error::custom)
+    }
+}
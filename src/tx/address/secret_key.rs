@@ -1,5 +1,8 @@
 use crate::{consts, tx::Address, Error, Result};
-use serde::{Serialize, Serializer};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use std::{
     fmt::{Debug, Display, Formatter, Result as FmtResult},
     hash::{Hash, Hasher},
@@ -29,7 +32,7 @@ impl Display for SecretKey {
 impl Eq for SecretKey {}
 impl Hash for SecretKey {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.0.as_bytes().hash(state)
+        self.0.as_bytes().hash(state);
     }
 }
 
@@ -50,3 +53,34 @@ impl Serialize for SecretKey {
         )
     }
 }
+
+impl<'de> Deserialize<'de> for SecretKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as Deserializer<'de>>::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(
+            consts::ED25519_DALEK_SECRET_KEY_TYPE_NAME,
+            SecretKeyVisitor,
+        )
+    }
+}
+
+/// Accepts only the bytes of a valid ed25519 secret key
+struct SecretKeyVisitor;
+
+impl<'de> Visitor<'de> for SecretKeyVisitor {
+    type Value = SecretKey;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(consts::ED25519_DALEK_SECRET_KEY_TYPE_NAME)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = <[u8; ed25519_dalek::SECRET_KEY_LENGTH]>::deserialize(deserializer)?;
+        SecretKey::from_slice(bytes).map_err(de::Error::custom)
+    }
+}
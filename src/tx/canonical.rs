@@ -0,0 +1,224 @@
+use crate::{
+    byte_reader::ByteReader,
+    consts::{self, msg},
+    tx::{Address, InputTx, OutputTx, PublicKey, SigHash, TxHash, TxIdx},
+    Error, Result, Signature,
+};
+use rust_decimal::Decimal;
+use std::convert::TryFrom;
+
+const TAG_GENESIS: u8 = 0;
+const TAG_UNSIGNED: u8 = 1;
+const TAG_SIGNED: u8 = 2;
+/// Largest scale a `Decimal` can have
+const MAX_SCALE: u32 = 28;
+
+/// Encodes a tx with `inputs` and `outputs` in the canonical format documented on `Tx::encode()`
+pub(crate) fn encode<'a>(
+    inputs: impl IntoIterator<Item = &'a InputTx>,
+    outputs: impl IntoIterator<Item = &'a OutputTx>,
+) -> Vec<u8> {
+    encode_with(inputs, outputs, true)
+}
+
+/// As `encode()`, with every signed input encoded as if it were unsigned.  This is what a tx's
+/// `TxHash` is computed over, so re-signing a tx does not change it.
+pub(crate) fn encode_without_signatures<'a>(
+    inputs: impl IntoIterator<Item = &'a InputTx>,
+    outputs: impl IntoIterator<Item = &'a OutputTx>,
+) -> Vec<u8> {
+    encode_with(inputs, outputs, false)
+}
+
+fn encode_with<'a>(
+    inputs: impl IntoIterator<Item = &'a InputTx>,
+    outputs: impl IntoIterator<Item = &'a OutputTx>,
+    with_signatures: bool,
+) -> Vec<u8> {
+    let inputs = inputs.into_iter().collect::<Vec<_>>();
+    let outputs = outputs.into_iter().collect::<Vec<_>>();
+
+    let mut bytes = consts::TX_ENCODING_MAGIC.to_vec();
+    bytes.push(consts::TX_ENCODING_VERSION);
+    write_u64(
+        &mut bytes,
+        u64::try_from(inputs.len()).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL)),
+    );
+    for input in inputs {
+        encode_input(&mut bytes, input, with_signatures);
+    }
+    write_u64(
+        &mut bytes,
+        u64::try_from(outputs.len()).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL)),
+    );
+    for output in outputs {
+        encode_value(&mut bytes, output.value());
+        write_len_prefixed(&mut bytes, output.address().as_bytes());
+    }
+    bytes
+}
+
+/// Decodes the inputs and outputs of a tx from `bytes`, which must hold exactly one tx in the
+/// canonical format and nothing else
+pub(crate) fn decode(bytes: &[u8]) -> Result<(Vec<InputTx>, Vec<OutputTx>)> {
+    let mut reader = ByteReader::new(bytes);
+    if reader.take(consts::TX_ENCODING_MAGIC.len()) != Some(&consts::TX_ENCODING_MAGIC[..]) {
+        Err(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_BAD_MAGIC))?;
+    }
+    match reader.u8() {
+        Some(consts::TX_ENCODING_VERSION) => (),
+        Some(version) => Err(Error::UnsupportedTxEncodingVersion(version))?,
+        None => Err(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_TRUNCATED))?,
+    }
+
+    let inputs = read_list(&mut reader, decode_input)?;
+    let outputs = read_list(&mut reader, |reader| {
+        let value = decode_value(reader)?;
+        let address = PublicKey::from_slice(read_len_prefixed(reader)?)
+            .map_err(|_| Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_BAD_ADDRESS))?;
+        Ok(OutputTx::new(value, address))
+    })?;
+    if !reader.is_empty() {
+        Err(Error::MalformedTxEncoding(
+            msg::ERR_TX_ENCODING_TRAILING_BYTES,
+        ))?;
+    }
+
+    // Every field has exactly one encoding, so anything which does not re-encode to the same bytes
+    // (a non-normalized value, a negative zero, ...) is not canonical
+    match encode(&inputs, &outputs) == bytes {
+        true => Ok((inputs, outputs)),
+        false => Err(Error::NonCanonicalTxEncoding),
+    }
+}
+
+fn encode_input(bytes: &mut Vec<u8>, input: &InputTx, with_signature: bool) {
+    match input {
+        InputTx::Genesis => bytes.push(TAG_GENESIS),
+        InputTx::Signed {
+            output_idx,
+            prev_tx_hash,
+            signature,
+            sighash,
+        } if with_signature => {
+            bytes.push(TAG_SIGNED);
+            write_len_prefixed(bytes, prev_tx_hash.as_ref());
+            write_u64(bytes, output_idx.0);
+            write_len_prefixed(bytes, &signature.0.to_bytes());
+            bytes.push(sighash.to_byte());
+        }
+        InputTx::Unsigned {
+            output_idx,
+            prev_tx_hash,
+        }
+        | InputTx::Signed {
+            output_idx,
+            prev_tx_hash,
+            ..
+        } => {
+            bytes.push(TAG_UNSIGNED);
+            write_len_prefixed(bytes, prev_tx_hash.as_ref());
+            write_u64(bytes, output_idx.0);
+        }
+    }
+}
+
+fn decode_input(reader: &mut ByteReader<'_>) -> Result<InputTx> {
+    let tag = reader
+        .u8()
+        .ok_or(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_TRUNCATED))?;
+    if tag == TAG_GENESIS {
+        return Ok(InputTx::Genesis);
+    }
+
+    let prev_tx_hash = TxHash::try_from(read_len_prefixed(reader)?)
+        .map_err(|_| Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_BAD_TX_HASH))?;
+    let output_idx = TxIdx(
+        reader
+            .u64()
+            .ok_or(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_TRUNCATED))?,
+    );
+    match tag {
+        TAG_UNSIGNED => Ok(InputTx::new(prev_tx_hash, output_idx)),
+        TAG_SIGNED => {
+            let signature = ed25519_dalek::Signature::from_bytes(read_len_prefixed(reader)?)
+                .map_err(|_| Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_BAD_SIGNATURE))?;
+            let sighash = reader
+                .u8()
+                .ok_or(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_TRUNCATED))
+                .and_then(|byte| {
+                    SigHash::from_byte(byte)
+                        .ok_or(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_BAD_SIGHASH))
+                })?;
+            InputTx::new(prev_tx_hash, output_idx).sign_with_sighash(Signature(signature), sighash)
+        }
+        _ => Err(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_BAD_TAG)),
+    }
+}
+
+/// Encodes `value` normalized (without trailing zeros) as its sign (1 for negative; zero is never
+/// negative), its scale and its 96-bit mantissa, little-endian
+fn encode_value(bytes: &mut Vec<u8>, value: Decimal) {
+    let value = value.normalize();
+    bytes.push(u8::from(value.is_sign_negative() && !value.is_zero()));
+    bytes.push(
+        u8::try_from(value.scale()).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL)),
+    );
+    // `Decimal::serialize()` lays out flags, then the mantissa's low, middle and high words
+    bytes.extend(value.serialize().iter().skip(4));
+}
+
+fn decode_value(reader: &mut ByteReader<'_>) -> Result<Decimal> {
+    let truncated = || Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_TRUNCATED);
+    let negative = match reader.u8().ok_or_else(truncated)? {
+        0 => false,
+        1 => true,
+        _ => Err(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_BAD_VALUE))?,
+    };
+    let scale = u32::from(reader.u8().ok_or_else(truncated)?);
+    if scale > MAX_SCALE {
+        Err(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_BAD_VALUE))?;
+    }
+    let mut words = reader
+        .take(12)
+        .ok_or_else(truncated)?
+        .chunks(4)
+        .filter_map(|word| <[u8; 4]>::try_from(word).ok().map(u32::from_le_bytes));
+    match (words.next(), words.next(), words.next()) {
+        (Some(lo), Some(mid), Some(hi)) => Ok(Decimal::from_parts(lo, mid, hi, negative, scale)),
+        _ => unreachable!("{}", msg::ERR_INTERNAL),
+    }
+}
+
+fn read_list<T>(
+    reader: &mut ByteReader<'_>,
+    mut read_item: impl FnMut(&mut ByteReader<'_>) -> Result<T>,
+) -> Result<Vec<T>> {
+    let count = reader
+        .u64()
+        .ok_or(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_TRUNCATED))?;
+    // Every item takes at least one byte, which bounds the allocation by the input's length
+    if count > u64::try_from(reader.len()).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL))
+    {
+        Err(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_TRUNCATED))?;
+    }
+    (0..count).map(|_| read_item(reader)).collect()
+}
+
+fn read_len_prefixed<'a>(reader: &mut ByteReader<'a>) -> Result<&'a [u8]> {
+    reader
+        .len_prefixed()
+        .ok_or(Error::MalformedTxEncoding(msg::ERR_TX_ENCODING_TRUNCATED))
+}
+
+fn write_u64(bytes: &mut Vec<u8>, n: u64) {
+    bytes.extend_from_slice(&n.to_le_bytes());
+}
+
+fn write_len_prefixed(bytes: &mut Vec<u8>, field: &[u8]) {
+    write_u64(
+        bytes,
+        u64::try_from(field.len()).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL)),
+    );
+    bytes.extend_from_slice(field);
+}
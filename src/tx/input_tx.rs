@@ -1,9 +1,12 @@
-use crate::{error::PanicError, tx::TxIdx, Error, Result, Signature, TxHash};
+use crate::{
+    error::PanicError,
+    tx::{SigHash, TxIdx},
+    Error, Result, Signature, TxHash, Utxo,
+};
 use bincode::serialize;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[allow(bare_trait_objects)]
-#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum InputTx {
     Genesis,
     Unsigned {
@@ -19,19 +22,27 @@ pub enum InputTx {
         prev_tx_hash: TxHash,
         /// The signature produced to check validity
         signature: Signature,
+        /// The parts of the tx `signature` commits to
+        sighash: SigHash,
     },
 }
 
 impl InputTx {
     #[must_use]
-    pub fn new(prev_tx_hash: TxHash, output_idx: TxIdx) -> Self {
+    pub const fn new(prev_tx_hash: TxHash, output_idx: TxIdx) -> Self {
         Self::Unsigned {
             output_idx,
             prev_tx_hash,
         }
     }
 
+    /// Signs this input with `signature` made under `SigHash::ALL`
     pub fn sign(self, signature: Signature) -> Result<Self> {
+        self.sign_with_sighash(signature, SigHash::ALL)
+    }
+
+    /// Signs this input with `signature` made under `sighash`
+    pub fn sign_with_sighash(self, signature: Signature, sighash: SigHash) -> Result<Self> {
         Ok(match self {
             Self::Unsigned {
                 output_idx,
@@ -40,11 +51,12 @@ impl InputTx {
             | Self::Signed {
                 output_idx,
                 prev_tx_hash,
-                signature: _,
+                ..
             } => Self::Signed {
                 output_idx,
                 prev_tx_hash,
                 signature,
+                sighash,
             },
             Self::Genesis => Err(Error::CannotSignGenesisBlock)?,
         })
@@ -59,7 +71,7 @@ impl InputTx {
             | Self::Signed {
                 output_idx,
                 prev_tx_hash,
-                signature: _,
+                ..
             } => Self::Unsigned {
                 output_idx,
                 prev_tx_hash,
@@ -68,8 +80,43 @@ impl InputTx {
         })
     }
 
+    /// Return the `Utxo` claimed by this input or `None` if this is a Genesis input
+    #[must_use]
+    pub fn utxo(&self) -> Option<Utxo> {
+        match self {
+            Self::Unsigned {
+                output_idx,
+                prev_tx_hash,
+            }
+            | Self::Signed {
+                output_idx,
+                prev_tx_hash,
+                ..
+            } => Some(Utxo::new(prev_tx_hash.clone(), *output_idx)),
+            Self::Genesis => None,
+        }
+    }
+
+    /// Return the signature of this input or `None` if it is not signed
+    #[must_use]
+    pub const fn signature(&self) -> Option<&Signature> {
+        match self {
+            Self::Signed { signature, .. } => Some(signature),
+            Self::Genesis | Self::Unsigned { .. } => None,
+        }
+    }
+
+    /// Return the `SigHash` this input's signature was made under or `None` if it is not signed
+    #[must_use]
+    pub const fn sighash(&self) -> Option<SigHash> {
+        match self {
+            Self::Signed { sighash, .. } => Some(*sighash),
+            Self::Genesis | Self::Unsigned { .. } => None,
+        }
+    }
+
     #[must_use]
     pub fn as_bytes(&self) -> Vec<u8> {
-        serialize(self).unwrap_or_else(|err| panic!(PanicError::Serialization(err)))
+        serialize(self).unwrap_or_else(|err| panic!("{}", PanicError::Serialization(err)))
     }
 }
@@ -1,8 +1,18 @@
-use crate::tx::tx_hash::TxHash;
+use crate::{
+    tx::{OutputTx, SigHash, TxHash, TxIdx},
+    Utxo,
+};
+use serde::{Deserialize, Serialize};
 
-/// Define the operations that can be performed on a tx
-#[derive(Debug)]
+/// Define the operations that can be performed on a tx, as logged by a `TxBuilder`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Operation {
-    Sign(TxHash),
-    Unsign,
+    /// An input claiming the `Utxo` was added
+    AddInput(Utxo),
+    /// The output was added
+    AddOutput(OutputTx),
+    /// The input at `TxIdx` was signed under `SigHash` while the tx's ID was `TxHash`
+    Sign(TxIdx, SigHash, TxHash),
+    /// The signature of the input at `TxIdx` was removed
+    Unsign(TxIdx),
 }
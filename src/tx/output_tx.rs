@@ -2,11 +2,11 @@ use crate::error::PanicError;
 use crate::tx::PublicKey;
 use bincode::serialize;
 use rust_decimal::Decimal;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct OutputTx {
-    /// Value in BitCoins of the output
+    /// Value in bitcoins of the output
     value: Decimal,
     /// The address (public key) of the recipient
     address: PublicKey,
@@ -18,8 +18,20 @@ impl OutputTx {
         Self { value, address }
     }
 
+    /// Return the value of this output
+    #[must_use]
+    pub const fn value(&self) -> Decimal {
+        self.value
+    }
+
+    /// Return the address (public key) of the recipient of this output
+    #[must_use]
+    pub const fn address(&self) -> &PublicKey {
+        &self.address
+    }
+
     #[must_use]
     pub fn as_bytes(&self) -> Vec<u8> {
-        serialize(self).unwrap_or_else(|err| panic!(PanicError::Serialization(err)))
+        serialize(self).unwrap_or_else(|err| panic!("{}", PanicError::Serialization(err)))
     }
 }
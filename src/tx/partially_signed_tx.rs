@@ -0,0 +1,222 @@
+use crate::{
+    consts::msg,
+    tx::{InputTx, OutputTx, PublicKey, SigHash, SigningState, Tx, TxBuilder, TxIdx},
+    Error, KeyPair, Result, Signature, UtxoStore,
+};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// A tx being signed by several key holders, each signing their own inputs on their own machine.
+///
+/// It carries the tx without signatures, the `OutputTx` each input spends (so any holder can
+/// check which inputs are theirs and verify signatures without a `UtxoPool`) and the signatures
+/// collected so far.  Copies signed by different holders are merged with `combine()`, and once
+/// every input is signed `finalize()` yields the signed `Tx`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(try_from = "PartiallySignedTxRepr")]
+pub struct PartiallySignedTx {
+    /// The tx, without signatures
+    tx: Tx,
+    /// The output spent by each input of `tx`
+    spent_outputs: Vec<OutputTx>,
+    /// The signature collected for each input of `tx`, if any, with the `SigHash` it was made
+    /// under
+    signatures: Vec<Option<(Signature, SigHash)>>,
+}
+
+impl PartiallySignedTx {
+    /// Creates a `PartiallySignedTx` for the tx `builder` holds, looking up the outputs its inputs
+    /// spend in `utxo_store`.  Any signatures the tx already carries are kept.  Fails if the tx
+    /// has no inputs or outputs, has a Genesis input or claims a `Utxo` that is not in
+    /// `utxo_store`.
+    pub fn new<S: SigningState>(
+        builder: TxBuilder<S>,
+        utxo_store: &impl UtxoStore,
+    ) -> Result<Self> {
+        let tx = builder.into_tx()?;
+        let spent_outputs = tx
+            .inputs()
+            .map(|input| {
+                let utxo = input.utxo().ok_or(Error::CannotSignGenesisBlock)?;
+                utxo_store
+                    .tx_output(&utxo)
+                    .cloned()
+                    .ok_or(Error::MissingUtxo(utxo))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let signatures = tx
+            .inputs()
+            .map(|input| input.signature().cloned().zip(input.sighash()))
+            .collect();
+        let unsigned_inputs = tx
+            .inputs()
+            .cloned()
+            .map(InputTx::unsign)
+            .collect::<Result<Vec<_>>>()?;
+        let tx = Tx::from_parts(unsigned_inputs, tx.outputs().cloned().collect())?;
+        Ok(Self {
+            tx,
+            spent_outputs,
+            signatures,
+        })
+    }
+
+    /// Return the tx being signed, without signatures
+    #[must_use]
+    pub const fn tx(&self) -> &Tx {
+        &self.tx
+    }
+
+    /// Return the output spent by the input at `idx` or `None` if there is no such input
+    #[must_use]
+    pub fn spent_output(&self, idx: TxIdx) -> Option<&OutputTx> {
+        usize::try_from(idx)
+            .ok()
+            .and_then(|position| self.spent_outputs.get(position))
+    }
+
+    /// Signs, under `SigHash::ALL`, every input spending an output addressed to `key_pair` which
+    /// lacks a valid signature, returning the number of inputs signed
+    pub fn sign(&mut self, key_pair: &KeyPair) -> usize {
+        self.sign_with_sighash(key_pair, SigHash::ALL)
+    }
+
+    /// Signs, under `sighash`, every input spending an output addressed to `key_pair` which lacks
+    /// a valid signature, returning the number of inputs signed.  An invalid signature is replaced.
+    /// Inputs `sighash` cannot sign (a `SigHash` `Single` input without a matching output) are
+    /// left as they are.
+    // The positions are collected first, as signing borrows `signatures` mutably
+    #[allow(clippy::needless_collect)]
+    pub fn sign_with_sighash(&mut self, key_pair: &KeyPair, sighash: SigHash) -> usize {
+        let public_key = key_pair.public_key();
+        let unsigned = self
+            .spent_outputs
+            .iter()
+            .enumerate()
+            .filter(|(position, spent_output)| {
+                *spent_output.address() == public_key && !self.is_signed(*position)
+            })
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        unsigned
+            .into_iter()
+            .filter_map(|position| {
+                let message = self.tx.signing_message(position, sighash)?;
+                let signature = self.signatures.get_mut(position)?;
+                *signature = Some((key_pair.sign(&message), sighash));
+                Some(())
+            })
+            .count()
+    }
+
+    /// Merges the signatures collected in `other` into this `PartiallySignedTx`, filling inputs
+    /// which lack a valid signature in this copy with those which have one in `other`.  Invalid
+    /// signatures in `other` are never copied.  Fails, leaving this copy unchanged, if `other` is
+    /// for a different tx.
+    pub fn combine(&mut self, other: &Self) -> Result<&mut Self> {
+        if self.tx.hash() != other.tx.hash() || self.spent_outputs != other.spent_outputs {
+            Err(Error::PartiallySignedTxMismatch)?;
+        }
+        // Both copies sign the same tx and spent outputs, so `other`'s signatures verify alike here
+        let verified = (0..self.signatures.len())
+            .filter(|position| !self.is_signed(*position) && other.is_signed(*position))
+            .collect::<Vec<_>>();
+        for position in verified {
+            if let (Some(signature), Some(other)) = (
+                self.signatures.get_mut(position),
+                other.signatures.get(position),
+            ) {
+                signature.clone_from(other);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Return the index of each input still lacking a valid signature and the public key that
+    /// must sign it
+    #[must_use]
+    pub fn missing_signatures(&self) -> Vec<(TxIdx, &PublicKey)> {
+        self.spent_outputs
+            .iter()
+            .enumerate()
+            .filter(|(position, _)| !self.is_signed(*position))
+            .map(|(position, spent_output)| {
+                (TxIdx::from_position(position), spent_output.address())
+            })
+            .collect()
+    }
+
+    /// Return true if every input has a valid signature
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        (0..self.spent_outputs.len()).all(|position| self.is_signed(position))
+    }
+
+    /// Builds the signed `Tx`.  Fails if any input still lacks a valid signature (see
+    /// `missing_signatures()`).
+    pub fn finalize(self) -> Result<Tx> {
+        let missing = self
+            .missing_signatures()
+            .into_iter()
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            Err(Error::PartiallySignedTxIncomplete(missing))?;
+        }
+        let signed_inputs = self
+            .tx
+            .inputs()
+            .cloned()
+            .zip(self.signatures)
+            .map(|(input, signature)| {
+                let (signature, sighash) =
+                    signature.unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL));
+                input.sign_with_sighash(signature, sighash)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Tx::from_parts(signed_inputs, self.tx.outputs().cloned().collect())
+    }
+
+    /// Return true if the input at `position` has a signature that verifies against the key of
+    /// the output it spends
+    fn is_signed(&self, position: usize) -> bool {
+        match (
+            self.signatures.get(position),
+            self.spent_outputs.get(position),
+        ) {
+            (Some(Some((signature, sighash))), Some(spent_output)) => self
+                .tx
+                .signing_message(position, *sighash)
+                .is_some_and(|message| spent_output.address().verify(&message, signature).is_ok()),
+            _ => false,
+        }
+    }
+}
+
+/// Serialized form of a `PartiallySignedTx`.  Deserialization rejects it unless it holds exactly
+/// one spent output and one signature slot per input of an unsigned tx.
+#[derive(Deserialize)]
+struct PartiallySignedTxRepr {
+    tx: Tx,
+    spent_outputs: Vec<OutputTx>,
+    signatures: Vec<Option<(Signature, SigHash)>>,
+}
+
+impl TryFrom<PartiallySignedTxRepr> for PartiallySignedTx {
+    type Error = Error;
+
+    fn try_from(repr: PartiallySignedTxRepr) -> Result<Self> {
+        let n_inputs = repr.tx.n_input_txs();
+        match repr.spent_outputs.len() == n_inputs
+            && repr.signatures.len() == n_inputs
+            && repr.tx.inputs().all(|input| input.signature().is_none())
+        {
+            true => Ok(Self {
+                tx: repr.tx,
+                spent_outputs: repr.spent_outputs,
+                signatures: repr.signatures,
+            }),
+            false => Err(Error::MalformedPartiallySignedTx),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use super::canonical;
+use crate::tx::{InputTx, OutputTx};
+use serde::{Deserialize, Serialize};
+
+const ALL: u8 = 0x01;
+const NONE: u8 = 0x02;
+const SINGLE: u8 = 0x03;
+const ANYONE_CAN_PAY: u8 = 0x80;
+
+/// Outputs an input's signature commits to
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum SigHashOutputs {
+    /// Every output; no output may be added, removed or altered
+    All,
+    /// No output; the outputs may be changed freely
+    None,
+    /// Only the output at the same index as the signed input
+    Single,
+}
+
+/// Selects which parts of a tx an input's signature commits to.
+///
+/// The signed input is always
+/// covered; the other inputs are covered unless `anyone_can_pay()` is set, and the outputs covered
+/// are given by `outputs()`.  The `SigHash` is stored alongside the signature in the tx, and is
+/// itself covered by the signature.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct SigHash {
+    outputs: SigHashOutputs,
+    anyone_can_pay: bool,
+}
+
+impl SigHash {
+    /// Commits to every input and every output
+    pub const ALL: Self = Self::new(SigHashOutputs::All, false);
+    /// Commits to every input and no output
+    pub const NONE: Self = Self::new(SigHashOutputs::None, false);
+    /// Commits to every input and the output at the same index as the signed input
+    pub const SINGLE: Self = Self::new(SigHashOutputs::Single, false);
+
+    #[must_use]
+    pub const fn new(outputs: SigHashOutputs, anyone_can_pay: bool) -> Self {
+        Self {
+            outputs,
+            anyone_can_pay,
+        }
+    }
+
+    /// Return this `SigHash` committing to the signed input only, so others may add inputs
+    #[must_use]
+    pub const fn with_anyone_can_pay(self) -> Self {
+        Self::new(self.outputs, true)
+    }
+
+    /// Return the outputs a signature with this `SigHash` commits to
+    #[must_use]
+    pub const fn outputs(self) -> SigHashOutputs {
+        self.outputs
+    }
+
+    /// Return true if a signature with this `SigHash` commits to the signed input only
+    #[must_use]
+    pub const fn anyone_can_pay(self) -> bool {
+        self.anyone_can_pay
+    }
+
+    /// Return the single-byte encoding of this `SigHash`
+    #[must_use]
+    pub const fn to_byte(self) -> u8 {
+        let outputs = match self.outputs {
+            SigHashOutputs::All => ALL,
+            SigHashOutputs::None => NONE,
+            SigHashOutputs::Single => SINGLE,
+        };
+        match self.anyone_can_pay {
+            true => outputs | ANYONE_CAN_PAY,
+            false => outputs,
+        }
+    }
+
+    /// Decodes a `SigHash` from its single-byte encoding (see `to_byte()`), or returns `None` if
+    /// `byte` does not encode one
+    #[must_use]
+    pub const fn from_byte(byte: u8) -> Option<Self> {
+        let outputs = match byte & !ANYONE_CAN_PAY {
+            ALL => SigHashOutputs::All,
+            NONE => SigHashOutputs::None,
+            SINGLE => SigHashOutputs::Single,
+            _ => return None,
+        };
+        Some(Self::new(outputs, byte & ANYONE_CAN_PAY != 0))
+    }
+}
+
+impl Default for SigHash {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Return the message signed by the input at `position` under `sighash`: the canonical encoding,
+/// without signatures, of the inputs and outputs `sighash` selects, followed by `sighash` itself.
+/// Returns `None` if there is no input at `position`, or if `sighash` selects the output at
+/// `position` and there is none.
+pub(crate) fn signing_message(
+    inputs: &[&InputTx],
+    outputs: &[&OutputTx],
+    position: usize,
+    sighash: SigHash,
+) -> Option<Vec<u8>> {
+    let input = inputs.get(position)?;
+    let inputs = match sighash.anyone_can_pay {
+        true => std::slice::from_ref(input),
+        false => inputs,
+    };
+    let outputs = match sighash.outputs {
+        SigHashOutputs::All => outputs,
+        SigHashOutputs::None => &[],
+        SigHashOutputs::Single => std::slice::from_ref(outputs.get(position)?),
+    };
+    let mut message =
+        canonical::encode_without_signatures(inputs.iter().copied(), outputs.iter().copied());
+    message.push(sighash.to_byte());
+    Some(message)
+}
@@ -1,35 +1,230 @@
-use super::non_empty_ext::NonEmptyExt;
+mod signing_state;
+
+use super::sighash;
+use crate::{tx::Operation, Error, KeyPair, Result, Signature, Utxo};
 pub use crate::{
-    tx::{Address, InputTx, OutputTx, PublicKey, SecretKey, Tx},
+    tx::{InputTx, OutputTx, PublicKey, SigHash, Tx},
     TxHash, TxIdx,
 };
-use crate::{Error, Result, Signature, Utxo};
-use nonempty::NonEmpty as NonEmptyVec;
 use rust_decimal::Decimal;
-use sha2::{Digest, Sha256};
+pub use signing_state::{FullySigned, Modifiable, PartiallySigned, SigningState, Unsigned};
+use std::{convert::TryFrom, marker::PhantomData};
 
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
-pub struct TxBuilder {
+/// Builds a `Tx`, tracking in its type how far the tx has got through signing.
+///
+/// Inputs and outputs are added to an `Unsigned` builder; signing an input makes it
+/// `PartiallySigned`, and once every input is signed `into_fully_signed()` makes it `FullySigned`,
+/// the only state that can `build()` a `Tx`.  Inputs and outputs may still be added while
+/// `PartiallySigned`, removing any signature which no longer covers the tx.  Every `Operation`
+/// applied is logged (see `operations()`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TxBuilder<S: SigningState = Unsigned> {
     input_txs: Vec<InputTx>,
     output_txs: Vec<OutputTx>,
+    operations: Vec<Operation>,
+    state: PhantomData<S>,
 }
 
-impl TxBuilder {
+impl TxBuilder<Unsigned> {
+    #[must_use]
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Signs the input at `idx` with `signature`, made under `SigHash::ALL`
+    pub fn add_signature(
+        self,
+        signature: Signature,
+        idx: TxIdx,
+    ) -> Result<TxBuilder<PartiallySigned>> {
+        self.add_signature_with_sighash(signature, SigHash::ALL, idx)
+    }
+
+    /// Signs the input at `idx` with `signature`, made under `sighash`
+    pub fn add_signature_with_sighash(
+        self,
+        signature: Signature,
+        sighash: SigHash,
+        idx: TxIdx,
+    ) -> Result<TxBuilder<PartiallySigned>> {
+        let mut builder = self.into_state::<PartiallySigned>();
+        builder.add_signature_with_sighash(signature, sighash, idx)?;
+        Ok(builder)
+    }
+
+    /// Signs the input at `idx` with `key_pair` under `SigHash::ALL`, so outputs must be added
+    /// first.  `key_pair` must own the `Utxo` the input claims for the tx to be valid.
+    pub fn sign_input(self, idx: TxIdx, key_pair: &KeyPair) -> Result<TxBuilder<PartiallySigned>> {
+        self.sign_input_with_sighash(idx, SigHash::ALL, key_pair)
+    }
+
+    /// Signs the input at `idx` with `key_pair` under `sighash` (see `signing_message()`)
+    pub fn sign_input_with_sighash(
+        self,
+        idx: TxIdx,
+        sighash: SigHash,
+        key_pair: &KeyPair,
+    ) -> Result<TxBuilder<PartiallySigned>> {
+        let mut builder = self.into_state::<PartiallySigned>();
+        builder.sign_input_with_sighash(idx, sighash, key_pair)?;
+        Ok(builder)
+    }
+}
+
+impl Default for TxBuilder<Unsigned> {
+    fn default() -> Self {
+        Self {
+            input_txs: Vec::new(),
+            output_txs: Vec::new(),
+            operations: Vec::new(),
+            state: PhantomData,
+        }
+    }
+}
+
+impl TxBuilder<PartiallySigned> {
+    /// Signs the input at `idx` with `signature`, made under `SigHash::ALL`
+    pub fn add_signature(&mut self, signature: Signature, idx: TxIdx) -> Result<&mut Self> {
+        self.add_signature_with_sighash(signature, SigHash::ALL, idx)
+    }
+
+    /// Signs the input at `idx` with `signature`, made under `sighash`
+    pub fn add_signature_with_sighash(
+        &mut self,
+        signature: Signature,
+        sighash: SigHash,
+        idx: TxIdx,
+    ) -> Result<&mut Self> {
+        let input = usize::try_from(idx)
+            .ok()
+            .and_then(|position| self.input_txs.get_mut(position))
+            .ok_or_else(|| Error::TxnIdxOutOfBounds(idx))?;
+        *input = input.clone().sign_with_sighash(signature, sighash)?;
+        let tx_hash = super::hash_tx(&self.input_txs, &self.output_txs);
+        self.operations.push(Operation::Sign(idx, sighash, tx_hash));
+        Ok(self)
+    }
+
+    /// Signs the input at `idx` with `key_pair` under `SigHash::ALL`
+    pub fn sign_input(&mut self, idx: TxIdx, key_pair: &KeyPair) -> Result<&mut Self> {
+        self.sign_input_with_sighash(idx, SigHash::ALL, key_pair)
+    }
+
+    /// Signs the input at `idx` with `key_pair` under `sighash` (see `signing_message()`)
+    pub fn sign_input_with_sighash(
+        &mut self,
+        idx: TxIdx,
+        sighash: SigHash,
+        key_pair: &KeyPair,
+    ) -> Result<&mut Self> {
+        let signature = key_pair.sign(&self.signing_message(idx, sighash)?);
+        self.add_signature_with_sighash(signature, sighash, idx)
+    }
+
+    /// Removes the signature of the input at `idx`, if it has one
+    pub fn unsign_input(&mut self, idx: TxIdx) -> Result<&mut Self> {
+        let input = usize::try_from(idx)
+            .ok()
+            .and_then(|position| self.input_txs.get_mut(position))
+            .ok_or_else(|| Error::TxnIdxOutOfBounds(idx))?;
+        *input = input.clone().unsign()?;
+        self.operations.push(Operation::Unsign(idx));
+        Ok(self)
+    }
+
+    /// Return the indices of the inputs not yet signed
+    #[must_use]
+    pub fn unsigned_inputs(&self) -> Vec<TxIdx> {
+        self.input_txs
+            .iter()
+            .enumerate()
+            .filter(|(_, input)| input.signature().is_none())
+            .map(|(position, _)| TxIdx::from_position(position))
+            .collect()
+    }
+
+    /// Moves the builder to `FullySigned` if every input is signed, or returns it unchanged if
+    /// any input (see `unsigned_inputs()`) is not
+    pub fn into_fully_signed(self) -> Result<TxBuilder<FullySigned>, Self> {
+        match self.unsigned_inputs().is_empty() {
+            true => Ok(self.into_state()),
+            false => Err(self),
+        }
+    }
+}
+
+impl TxBuilder<FullySigned> {
+    /// Builds the signed `Tx`
+    pub fn build(self) -> Result<Tx> {
+        self.into_tx()
+    }
+}
+
+impl<S: Modifiable> TxBuilder<S> {
+    /// Adds an input claiming output `output_idx` of the tx `prev_tx_hash`.  Signatures covering
+    /// every input (those not made under `anyone_can_pay()`) no longer cover the tx, so are
+    /// removed.
     pub fn add_input(&mut self, prev_tx_hash: TxHash, output_idx: TxIdx) -> &mut Self {
+        let signed = self.signed_messages();
+        self.operations.push(Operation::AddInput(Utxo::new(
+            prev_tx_hash.clone(),
+            output_idx,
+        )));
         self.input_txs.push(InputTx::new(prev_tx_hash, output_idx));
-        self
+        self.remove_stale_signatures(signed)
     }
 
+    /// Adds an output paying `value` to `address`.  Signatures covering every output (those made
+    /// under `SigHashOutputs::All`) no longer cover the tx, so are removed.
     pub fn add_output(&mut self, value: Decimal, address: PublicKey) -> &mut Self {
-        self.output_txs.push(OutputTx::new(value, address));
+        let signed = self.signed_messages();
+        let output_tx = OutputTx::new(value, address);
+        self.operations
+            .push(Operation::AddOutput(output_tx.clone()));
+        self.output_txs.push(output_tx);
+        self.remove_stale_signatures(signed)
+    }
+
+    /// Return the position, `SigHash` and signing message of each signed input
+    fn signed_messages(&self) -> Vec<(usize, SigHash, Option<Vec<u8>>)> {
+        self.input_txs
+            .iter()
+            .enumerate()
+            .filter_map(|(position, input)| {
+                let sighash = input.sighash()?;
+                let message = self
+                    .signing_message(TxIdx::from_position(position), sighash)
+                    .ok();
+                Some((position, sighash, message))
+            })
+            .collect()
+    }
+
+    /// Removes the signature of each input of `signed` whose signing message has changed since
+    /// `signed` was taken, logging an `Operation::Unsign` for each
+    fn remove_stale_signatures(
+        &mut self,
+        signed: Vec<(usize, SigHash, Option<Vec<u8>>)>,
+    ) -> &mut Self {
+        for (position, sighash, message) in signed {
+            let idx = TxIdx::from_position(position);
+            if self.signing_message(idx, sighash).ok() == message {
+                continue;
+            }
+            if let Some(input) = self.input_txs.get_mut(position) {
+                if let Ok(unsigned) = input.clone().unsign() {
+                    *input = unsigned;
+                    self.operations.push(Operation::Unsign(idx));
+                }
+            }
+        }
         self
     }
+}
 
+impl<S: SigningState> TxBuilder<S> {
     // TODO: This is about as expensive as possible; refactor using `HashMap` and less (no?) copying
+    #[allow(dead_code, clippy::unnecessary_wraps)]
     fn remove_input(&mut self, utxo: &Utxo) -> Result<&mut Self> {
         self.input_txs = self
             .input_txs
@@ -40,87 +235,84 @@ impl TxBuilder {
         Ok(self)
     }
 
-    fn raw_tx_unsigned(&self, idx: usize) -> Option<Vec<u8>> {
-        self.input_txs.get(idx).map(|tx| {
-            match tx {
-                InputTx::Signed {
-                    output_idx: _,
-                    prev_tx_hash: _,
-                    signature: _,
-                } => tx
-                    .clone()
-                    .unsign()
-                    .unwrap_or_else(|err| unreachable!(err))
-                    .as_bytes(),
-                t => t.as_bytes(),
-            }
-            .into_iter()
-            .chain(
-                self.output_txs
-                    .iter()
-                    .flat_map(|tx| tx.as_bytes().into_iter()),
-            )
-            .collect()
-        })
-    }
-
-    pub fn add_signature(&mut self, signature: Signature, idx: TxIdx) -> Result<&mut Self> {
-        self.input_txs
-            .get_mut(usize::from(idx))
-            .ok_or_else(|| Error::TxnIdxOutOfBounds(idx))
-            .map(|el| el.clone().sign(signature).map(|signed| *el = signed))??;
-        Ok(self)
+    /// Return the message the input at `idx` must sign under `sighash`.  It covers the inputs and
+    /// outputs `sighash` selects as they currently stand, so inputs and outputs it selects must be
+    /// added before signing.
+    pub fn signing_message(&self, idx: TxIdx, sighash: SigHash) -> Result<Vec<u8>> {
+        let position = usize::try_from(idx)
+            .ok()
+            .filter(|position| *position < self.input_txs.len())
+            .ok_or_else(|| Error::TxnIdxOutOfBounds(idx))?;
+        sighash::signing_message(
+            &self.input_txs.iter().collect::<Vec<_>>(),
+            &self.output_txs.iter().collect::<Vec<_>>(),
+            position,
+            sighash,
+        )
+        .ok_or_else(|| Error::SigHashSingleWithoutOutput(idx))
     }
 
-    fn raw_tx(&self) -> Vec<u8> {
-        self.input_txs
-            .iter()
-            .flat_map(|tx| tx.as_bytes().into_iter())
-            .chain(
-                self.output_txs
-                    .iter()
-                    .flat_map(|tx| tx.as_bytes().into_iter()),
-            )
-            .collect()
+    /// Return the log of every `Operation` applied to the tx, oldest first
+    #[must_use]
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
     }
 
-    pub fn build(self) -> Result<Tx> {
-        Ok(Tx {
-            hash: self.hash_tx(),
-            inputs: NonEmptyVec::try_from(self.input_txs)
-                .ok_or_else(|| Error::CannotBuildTxWithoutInputTxs)?,
-            outputs: NonEmptyVec::try_from(self.output_txs)
-                .ok_or_else(|| Error::CannotBuildTxWithoutOutputTxs)?,
-        })
+    /// Assembles a `Tx` from the inputs and outputs as they stand, whatever the builder's state
+    pub(crate) fn into_tx(self) -> Result<Tx> {
+        Tx::from_parts(self.input_txs, self.output_txs)
     }
 
-    fn hash_tx(&self) -> TxHash {
-        let mut hasher = Sha256::new();
-        hasher.input(&self.raw_tx());
-        TxHash(hasher.result().as_slice().to_vec())
+    fn into_state<T: SigningState>(self) -> TxBuilder<T> {
+        TxBuilder {
+            input_txs: self.input_txs,
+            output_txs: self.output_txs,
+            operations: self.operations,
+            state: PhantomData,
+        }
     }
 
+    #[must_use]
     pub const fn input_txs(&self) -> &Vec<InputTx> {
         &self.input_txs
     }
 
+    #[must_use]
     pub const fn output_txs(&self) -> &Vec<OutputTx> {
         &self.output_txs
     }
 
+    #[must_use]
     pub fn input_tx(&self, idx: TxIdx) -> Option<&InputTx> {
-        self.input_txs.get(usize::from(idx))
+        usize::try_from(idx)
+            .ok()
+            .and_then(|position| self.input_txs.get(position))
     }
 
+    #[must_use]
     pub fn output_tx(&self, idx: TxIdx) -> Option<&OutputTx> {
-        self.output_txs.get(usize::from(idx))
+        usize::try_from(idx)
+            .ok()
+            .and_then(|position| self.output_txs.get(position))
     }
 
-    pub fn n_input_txs(&self) -> usize {
+    #[must_use]
+    pub const fn n_input_txs(&self) -> usize {
         self.input_txs.len()
     }
 
-    pub fn n_output_txs(&self) -> usize {
+    #[must_use]
+    pub const fn n_output_txs(&self) -> usize {
         self.output_txs.len()
     }
+
+    /// Return the index of each input added so far, in order
+    pub fn input_idxs(&self) -> impl Iterator<Item = TxIdx> {
+        TxIdx::range(self.input_txs.len())
+    }
+
+    /// Return the index of each output added so far, in order
+    pub fn output_idxs(&self) -> impl Iterator<Item = TxIdx> {
+        TxIdx::range(self.output_txs.len())
+    }
 }
@@ -0,0 +1,32 @@
+/// Stage of a `TxBuilder` in a tx's signing lifecycle:
+/// `Unsigned` → `PartiallySigned` → `FullySigned`
+pub trait SigningState: sealed::Sealed {}
+
+/// Stages in which inputs and outputs may still be added
+pub trait Modifiable: SigningState {}
+
+/// No input has been signed yet
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Unsigned;
+
+/// At least one input has been signed
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PartiallySigned;
+
+/// Every input has been signed; the tx is ready to be built and broadcast
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FullySigned;
+
+impl SigningState for Unsigned {}
+impl SigningState for PartiallySigned {}
+impl SigningState for FullySigned {}
+impl Modifiable for Unsigned {}
+impl Modifiable for PartiallySigned {}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::Unsigned {}
+    impl Sealed for super::PartiallySigned {}
+    impl Sealed for super::FullySigned {}
+}
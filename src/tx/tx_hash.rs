@@ -1,4 +1,81 @@
-use serde::Serialize;
+use crate::{consts::TX_HASH_LENGTH, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Debug, Display, Formatter},
+    str::FromStr,
+};
 
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
-pub struct TxHash(pub(crate) Vec<u8>);
+/// SHA-256 hash identifying a tx.  Displays, and parses from, 64 hex digits.
+#[derive(Clone, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct TxHash(pub(crate) [u8; TX_HASH_LENGTH]);
+
+impl TxHash {
+    #[must_use]
+    pub const fn new(bytes: [u8; TX_HASH_LENGTH]) -> Self {
+        Self(bytes)
+    }
+
+    #[must_use]
+    pub const fn to_bytes(&self) -> [u8; TX_HASH_LENGTH] {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for TxHash {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u8; TX_HASH_LENGTH]> for TxHash {
+    fn from(bytes: [u8; TX_HASH_LENGTH]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for TxHash {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        <[u8; TX_HASH_LENGTH]>::try_from(bytes)
+            .map(Self)
+            .map_err(|_| Error::TxHashLength(bytes.len()))
+    }
+}
+
+impl Debug for TxHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "TxHash({self})")
+    }
+}
+
+impl Display for TxHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl FromStr for TxHash {
+    type Err = Error;
+
+    /// Parses a `TxHash` from 64 hex digits, in either case
+    fn from_str(hex: &str) -> Result<Self> {
+        let invalid = || Error::ParseTxHash(hex.to_string());
+        // `from_str_radix()` accepts a leading sign, so every character is checked up front
+        if !hex.chars().all(|digit| digit.is_ascii_hexdigit()) {
+            Err(invalid())?;
+        }
+        let bytes = hex
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(invalid)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::try_from(bytes.as_slice()).map_err(|_| invalid())
+    }
+}
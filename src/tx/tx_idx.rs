@@ -1,12 +1,66 @@
-use serde::Serialize;
+use crate::{consts::msg, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+};
 
-#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
-pub struct TxIdx(u64);
+/// Index of an input or output within a tx
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct TxIdx(pub(crate) u64);
 
-impl From<TxIdx> for usize {
-    #[allow(clippy::cast_possible_truncation)]
+impl TxIdx {
+    #[must_use]
+    pub const fn new(idx: u64) -> Self {
+        Self(idx)
+    }
+
+    /// Return the `TxIdx` of the element at `position` in a tx's inputs or outputs
+    #[must_use]
+    pub(crate) fn from_position(position: usize) -> Self {
+        Self(u64::try_from(position).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL)))
+    }
+
+    /// Return the indices of a list of `len` inputs or outputs, in order
+    pub(crate) fn range(len: usize) -> impl Iterator<Item = Self> {
+        (0..len).map(Self::from_position)
+    }
+}
+
+impl From<u32> for TxIdx {
+    fn from(idx: u32) -> Self {
+        Self(u64::from(idx))
+    }
+}
+
+impl From<TxIdx> for u64 {
     fn from(idx: TxIdx) -> Self {
-        // TODO: Temporary cast until backing lists are refactored to `HashMaps`
-        idx.0 as Self
+        idx.0
+    }
+}
+
+impl TryFrom<usize> for TxIdx {
+    type Error = Error;
+
+    fn try_from(position: usize) -> Result<Self> {
+        u64::try_from(position)
+            .map(Self)
+            .map_err(|_| Error::PositionExceedsTxIdx(position))
+    }
+}
+
+impl TryFrom<TxIdx> for usize {
+    type Error = Error;
+
+    /// Fails if `idx` does not fit in a `usize`, as on 32-bit targets, in which case it cannot
+    /// index any tx's inputs or outputs
+    fn try_from(idx: TxIdx) -> Result<Self> {
+        Self::try_from(idx.0).map_err(|_| Error::TxIdxExceedsUsize(idx))
+    }
+}
+
+impl Display for TxIdx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
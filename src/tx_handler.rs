@@ -1,35 +1,290 @@
-use crate::Tx;
+mod conflict_policy;
+mod epoch_report;
+mod epoch_simulation;
+
+pub use conflict_policy::{
+    Conflict, ConflictPolicy, FirstSeen, HighestFee, HighestFeeRate, LowestTxHash, ProposedTx,
+};
+pub use epoch_report::{EpochReport, RejectedTx, RejectionReason};
+pub use epoch_simulation::EpochSimulation;
+
+use crate::{
+    consts::msg,
+    error::TxValidationError,
+    utxo_pool::{UtxoPoolOverlay, UtxoView},
+    Error, Result, Tx, TxHash, Utxo, UtxoDelta, UtxoPool, UtxoStore,
+};
+use rust_decimal::Decimal;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug)]
-pub struct TxHandler {}
+pub struct TxHandler<P: ConflictPolicy = LowestTxHash, S: UtxoStore = UtxoPool> {
+    /// The handler's current collection of UTXOs
+    utxo_pool: S,
+    /// Decides which tx is accepted when proposed txs claim the same `Utxo`
+    conflict_policy: P,
+}
 
 impl TxHandler {
     /// Creates a public ledger whose current `UtxoPool` (collection of unspent transaction
-    /// outputs) is `utxo_pool`. This should make a copy of `utxo_pool` by using the
-    /// `UTXOPool::clone()` method.
-    #[allow(clippy::new_without_default)]
+    /// outputs) is `utxo_pool`. This makes a copy of `utxo_pool` by using the
+    /// `UtxoPool::clone()` method, which takes constant time.  Double spends are resolved in
+    /// favour of the lowest `TxHash`.
+    #[must_use]
+    pub fn new(utxo_pool: &UtxoPool) -> Self {
+        Self::with_conflict_policy(utxo_pool, LowestTxHash)
+    }
+}
+
+impl<P: ConflictPolicy> TxHandler<P> {
+    /// Creates a public ledger whose current `UtxoPool` is a copy of `utxo_pool`, resolving
+    /// double spends with `conflict_policy`
     #[must_use]
-    pub fn new() -> Self {
-        unimplemented!()
+    pub fn with_conflict_policy(utxo_pool: &UtxoPool, conflict_policy: P) -> Self {
+        Self::with_store(utxo_pool.clone(), conflict_policy)
+    }
+
+    /// Handles each epoch by receiving an unordered array of proposed transactions, checking each
+    /// transaction for correctness, returning a mutually valid array of accepted transactions, and
+    /// updating the current UTXO pool as appropriate.
+    ///
+    /// Txs spending outputs created by other txs in `possible_txs` are accepted once their parents
+    /// have been.  Txs which become valid at the same point in the epoch and claim the same `Utxo`
+    /// are resolved by the handler's `ConflictPolicy`.  Unless the policy itself depends on it
+    /// (e.g. `FirstSeen`), the result does not depend on the order of `possible_txs`.
+    pub fn handle_txs(&mut self, possible_txs: &[Tx]) -> Vec<Tx> {
+        self.handle_txs_with_report(possible_txs).into_accepted()
+    }
+
+    /// As `handle_txs()`, additionally returning every tx rejected because a tx accepted in the
+    /// same epoch spent a `Utxo` it claims, along with the hash of that winning tx.
+    pub fn handle_txs_with_conflicts(&mut self, possible_txs: &[Tx]) -> (Vec<Tx>, Vec<Conflict>) {
+        self.try_handle_txs_with_conflicts(possible_txs)
+            .unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL))
+    }
+
+    /// As `handle_txs()`, returning an `EpochReport` detailing the accepted txs, why each other
+    /// tx was rejected, the `Utxo`s created and spent and the total fees collected.
+    pub fn handle_txs_with_report(&mut self, possible_txs: &[Tx]) -> EpochReport {
+        // An epoch only spends `Utxo`s in the pool and creates outputs worth no more than those it
+        // spends, so a `UtxoPool` always records it
+        self.try_handle_txs_with_report(possible_txs)
+            .unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL))
+    }
+}
+
+impl<P: ConflictPolicy, S: UtxoStore> TxHandler<P, S> {
+    /// Creates a public ledger over `utxo_store`, resolving double spends with `conflict_policy`
+    #[must_use]
+    pub const fn with_store(utxo_store: S, conflict_policy: P) -> Self {
+        Self {
+            utxo_pool: utxo_store,
+            conflict_policy,
+        }
+    }
+
+    /// Return the handler's current collection of UTXOs
+    #[must_use]
+    pub const fn utxo_pool(&self) -> &S {
+        &self.utxo_pool
+    }
+
+    /// Return the handler's current collection of UTXOs for modification, e.g. to roll a
+    /// `UtxoPool` back to a `Checkpoint` on a chain reorganisation
+    pub const fn utxo_pool_mut(&mut self) -> &mut S {
+        &mut self.utxo_pool
+    }
+
+    #[must_use]
+    pub const fn conflict_policy(&self) -> &P {
+        &self.conflict_policy
     }
 
     /// return `true` if:
     /// (1) all outputs claimed by `tx` are in the current UTXO pool,
-    /// (2) the signatures on each input of `tx` are valid,
+    /// (2) the signature on each input of `tx` is valid over the parts of `tx` its `SigHash`
+    ///     selects,
     /// (3) no UTXO is claimed multiple times by `tx`,
     /// (4) all of `tx`s output values are non-negative, and
     /// (5) the sum of `tx`s input values is greater than or equal to the sum of its output
     ///     values; and `false` otherwise.
     #[must_use]
     pub fn is_valid_tx(&self, tx: &Tx) -> bool {
-        unimplemented!()
+        self.validate_tx(tx).is_ok()
     }
 
-    /// Handles each epoch by receiving an unordered array of proposed transactions, checking each
-    /// transaction for correctness, returning a mutually valid array of accepted transactions, and
-    /// updating the current UTXO pool as appropriate.
+    /// Checks `tx` against the rules listed on `is_valid_tx()`, returning the first rule `tx`
+    /// violates as a `TxValidationError`.
+    pub fn validate_tx(&self, tx: &Tx) -> Result<(), TxValidationError> {
+        checked_fee(&UtxoPoolOverlay::new(&self.utxo_pool), tx).map(|_| ())
+    }
+
+    /// As `handle_txs()`, for any `UtxoStore`.  Fails, accepting no txs, if the store cannot
+    /// record the changes.
+    pub fn try_handle_txs(&mut self, possible_txs: &[Tx]) -> Result<Vec<Tx>> {
+        self.try_handle_txs_with_report(possible_txs)
+            .map(EpochReport::into_accepted)
+    }
+
+    /// As `handle_txs_with_conflicts()`, for any `UtxoStore`.  Fails, accepting no txs, if the
+    /// store cannot record the changes.
+    pub fn try_handle_txs_with_conflicts(
+        &mut self,
+        possible_txs: &[Tx],
+    ) -> Result<(Vec<Tx>, Vec<Conflict>)> {
+        let report = self.try_handle_txs_with_report(possible_txs)?;
+        let conflicts = report
+            .rejected()
+            .iter()
+            .filter_map(|rejected| match rejected.reason() {
+                RejectionReason::Conflict { winner, utxo } => Some(Conflict::new(
+                    rejected.tx().clone(),
+                    winner.clone(),
+                    utxo.clone(),
+                )),
+                RejectionReason::Invalid(_) | RejectionReason::Duplicate => None,
+            })
+            .collect();
+        Ok((report.into_accepted(), conflicts))
+    }
+
+    /// As `handle_txs_with_report()`, for any `UtxoStore`.  Fails, accepting no txs, if the store
+    /// cannot record the changes.
+    pub fn try_handle_txs_with_report(&mut self, possible_txs: &[Tx]) -> Result<EpochReport> {
+        let (report, delta) = self.process_epoch(possible_txs);
+        self.utxo_pool.apply_delta(&delta)?;
+        Ok(report)
+    }
+
+    /// Runs full epoch processing of `possible_txs` against a scratch overlay of the handler's
+    /// `UtxoStore`, returning what `handle_txs_with_report()` would report and the changes it would
+    /// make to the store.  The store itself is left untouched.
     #[must_use]
-    pub fn handle_txs(&self, possible_txs: &[Tx]) -> Vec<Tx> {
-        unimplemented!()
+    pub fn simulate_txs(&self, possible_txs: &[Tx]) -> EpochSimulation {
+        let (report, delta) = self.process_epoch(possible_txs);
+        EpochSimulation::new(report, delta, self.utxo_pool.version())
+    }
+
+    /// Applies the changes of `simulation` to the handler's `UtxoStore`, provided the store has not
+    /// changed since the simulation was run.
+    pub fn commit_simulation(&mut self, simulation: EpochSimulation) -> Result<EpochReport> {
+        match simulation.base_version() == self.utxo_pool.version() {
+            true => {
+                self.utxo_pool.apply_delta(simulation.delta())?;
+                Ok(simulation.into_report())
+            }
+            false => Err(Error::StaleSimulation {
+                simulated: simulation.base_version(),
+                current: self.utxo_pool.version(),
+            }),
+        }
+    }
+
+    fn process_epoch(&self, possible_txs: &[Tx]) -> (EpochReport, UtxoDelta) {
+        let mut overlay = UtxoPoolOverlay::new(&self.utxo_pool);
+        let mut rejected = Vec::new();
+        let mut seen = HashSet::new();
+        let mut pending = Vec::new();
+        for (position, tx) in possible_txs.iter().enumerate() {
+            match seen.insert(tx.witness_hash()) {
+                true => pending.push((position, tx)),
+                false => rejected.push((
+                    position,
+                    RejectedTx::new(tx.clone(), RejectionReason::Duplicate),
+                )),
+            }
+        }
+        pending.sort_by(|(_, lhs), (_, rhs)| {
+            (lhs.hash(), lhs.witness_hash()).cmp(&(rhs.hash(), rhs.witness_hash()))
+        });
+
+        let mut accepted = Vec::new();
+        let mut created = Vec::new();
+        let mut spent_by = HashMap::<Utxo, TxHash>::new();
+        let mut spent = Vec::new();
+        let mut total_fees = Decimal::new(0, 0);
+        loop {
+            let mut valid = pending
+                .iter()
+                .filter_map(|&(position, tx)| {
+                    checked_fee(&overlay, tx)
+                        .ok()
+                        .map(|fee| ProposedTx::new(tx, fee, position))
+                })
+                .collect::<Vec<_>>();
+            if valid.is_empty() {
+                break;
+            }
+            let conflict_policy = &self.conflict_policy;
+            valid.sort_by(|lhs, rhs| {
+                conflict_policy.prefer(lhs, rhs).then_with(|| {
+                    (lhs.tx().hash(), lhs.tx().witness_hash())
+                        .cmp(&(rhs.tx().hash(), rhs.tx().witness_hash()))
+                })
+            });
+
+            let mut resolved = HashSet::new();
+            for proposed in valid {
+                let tx = proposed.tx();
+                resolved.insert(tx.witness_hash());
+                let lost_to = tx
+                    .spent_utxos()
+                    .find_map(|utxo| spent_by.get(&utxo).map(|winner| (utxo, winner)));
+                if let Some((utxo, winner)) = lost_to {
+                    let reason = RejectionReason::Conflict {
+                        winner: winner.clone(),
+                        utxo,
+                    };
+                    rejected.push((proposed.position(), RejectedTx::new(tx.clone(), reason)));
+                } else {
+                    spent_by.extend(tx.spent_utxos().map(|utxo| (utxo, tx.hash().clone())));
+                    spent.extend(tx.spent_utxos());
+                    created.extend(tx.created_utxos());
+                    total_fees = total_fees
+                        .checked_add(proposed.fee())
+                        .unwrap_or(Decimal::MAX);
+                    overlay.apply_tx(tx);
+                    accepted.push(tx.clone());
+                }
+            }
+            pending.retain(|(_, tx)| !resolved.contains(tx.witness_hash()));
+        }
+
+        // Txs left pending whose claim was spent by an accepted tx lost a conflict to it
+        rejected.extend(pending.into_iter().filter_map(|(position, tx)| {
+            checked_fee(&overlay, tx).err().map(|err| {
+                let reason = match err {
+                    TxValidationError::MissingUtxo(utxo) => match spent_by.get(&utxo) {
+                        Some(winner) => RejectionReason::Conflict {
+                            winner: winner.clone(),
+                            utxo,
+                        },
+                        None => RejectionReason::Invalid(TxValidationError::MissingUtxo(utxo)),
+                    },
+                    err => RejectionReason::Invalid(err),
+                };
+                (position, RejectedTx::new(tx.clone(), reason))
+            })
+        }));
+        rejected.sort_by_key(|&(position, _)| position);
+
+        let report = EpochReport::new(
+            accepted,
+            rejected.into_iter().map(|(_, rejected)| rejected).collect(),
+            created,
+            spent,
+            total_fees,
+        );
+        (report, overlay.into_delta())
     }
 }
+
+/// Checks `tx` against `utxo_pool` (see `TxHandler::is_valid_tx()` for the rules applied) and
+/// returns its fee: the sum of its input values less the sum of its output values.
+pub(crate) fn checked_fee(
+    utxo_pool: &impl UtxoView,
+    tx: &Tx,
+) -> Result<Decimal, TxValidationError> {
+    tx.verify_signatures_against(utxo_pool)?;
+    tx.fee_against(utxo_pool)
+}
@@ -0,0 +1,133 @@
+use crate::{Tx, TxHash, Utxo};
+use rust_decimal::Decimal;
+use std::cmp::Ordering;
+
+/// A proposed tx which is valid at the current point in the epoch, as seen by a `ConflictPolicy`
+#[derive(Clone, Copy, Debug)]
+pub struct ProposedTx<'a> {
+    tx: &'a Tx,
+    /// Sum of the tx's input values less the sum of its output values
+    fee: Decimal,
+    /// Index of the tx in the slice handed to `TxHandler::handle_txs()`
+    position: usize,
+    /// Size of the tx's canonical encoding in bytes, computed once as policies compare it often
+    n_bytes: usize,
+}
+
+impl<'a> ProposedTx<'a> {
+    pub(crate) fn new(tx: &'a Tx, fee: Decimal, position: usize) -> Self {
+        Self {
+            tx,
+            fee,
+            position,
+            n_bytes: tx.n_bytes(),
+        }
+    }
+
+    #[must_use]
+    pub const fn tx(&self) -> &'a Tx {
+        self.tx
+    }
+
+    #[must_use]
+    pub const fn fee(&self) -> Decimal {
+        self.fee
+    }
+
+    /// Return the fee paid per byte of the tx
+    #[must_use]
+    pub fn fee_rate(&self) -> Decimal {
+        self.fee
+            .checked_div(Decimal::from(self.n_bytes))
+            .unwrap_or(self.fee)
+    }
+
+    #[must_use]
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+}
+
+/// Decides which of two proposed txs claiming the same `Utxo` is accepted in an epoch
+pub trait ConflictPolicy {
+    /// Return `Ordering::Less` if `lhs` should be accepted in preference to `rhs`.  Txs the
+    /// policy considers equal are ordered by `TxHash`.
+    fn prefer(&self, lhs: &ProposedTx<'_>, rhs: &ProposedTx<'_>) -> Ordering;
+}
+
+/// Accepts the tx which appears first in the slice handed to `TxHandler::handle_txs()`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FirstSeen;
+
+impl ConflictPolicy for FirstSeen {
+    fn prefer(&self, lhs: &ProposedTx<'_>, rhs: &ProposedTx<'_>) -> Ordering {
+        lhs.position().cmp(&rhs.position())
+    }
+}
+
+/// Accepts the tx paying the highest fee
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HighestFee;
+
+impl ConflictPolicy for HighestFee {
+    fn prefer(&self, lhs: &ProposedTx<'_>, rhs: &ProposedTx<'_>) -> Ordering {
+        rhs.fee().cmp(&lhs.fee())
+    }
+}
+
+/// Accepts the tx paying the highest fee per byte
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HighestFeeRate;
+
+impl ConflictPolicy for HighestFeeRate {
+    fn prefer(&self, lhs: &ProposedTx<'_>, rhs: &ProposedTx<'_>) -> Ordering {
+        rhs.fee_rate().cmp(&lhs.fee_rate())
+    }
+}
+
+/// Accepts the tx with the lowest `TxHash`; the outcome does not depend on the order in which
+/// txs are proposed
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LowestTxHash;
+
+impl ConflictPolicy for LowestTxHash {
+    fn prefer(&self, lhs: &ProposedTx<'_>, rhs: &ProposedTx<'_>) -> Ordering {
+        lhs.tx().hash().cmp(rhs.tx().hash())
+    }
+}
+
+/// A proposed tx rejected because another tx accepted in the same epoch spent a `Utxo` it claims
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    rejected: Tx,
+    winner: TxHash,
+    utxo: Utxo,
+}
+
+impl Conflict {
+    pub(crate) const fn new(rejected: Tx, winner: TxHash, utxo: Utxo) -> Self {
+        Self {
+            rejected,
+            winner,
+            utxo,
+        }
+    }
+
+    /// Return the tx which lost the conflict
+    #[must_use]
+    pub const fn rejected(&self) -> &Tx {
+        &self.rejected
+    }
+
+    /// Return the hash of the accepted tx which spent `utxo()`
+    #[must_use]
+    pub const fn winner(&self) -> &TxHash {
+        &self.winner
+    }
+
+    /// Return the `Utxo` both txs claimed
+    #[must_use]
+    pub const fn utxo(&self) -> &Utxo {
+        &self.utxo
+    }
+}
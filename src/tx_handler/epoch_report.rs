@@ -0,0 +1,101 @@
+use crate::{error::TxValidationError, Tx, TxHash, Utxo};
+use rust_decimal::Decimal;
+
+/// Why a proposed tx was not accepted in an epoch
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RejectionReason {
+    /// The tx failed validation against the `UtxoPool` as it stood at the end of the epoch
+    Invalid(TxValidationError),
+    /// The accepted tx `winner` spent `utxo`, which this tx also claims
+    Conflict { winner: TxHash, utxo: Utxo },
+    /// The same tx, signatures included, was proposed more than once; only its first occurrence
+    /// is considered
+    Duplicate,
+}
+
+/// A proposed tx which was not accepted in an epoch, and why
+#[derive(Clone, Debug)]
+pub struct RejectedTx {
+    tx: Tx,
+    reason: RejectionReason,
+}
+
+impl RejectedTx {
+    pub(crate) const fn new(tx: Tx, reason: RejectionReason) -> Self {
+        Self { tx, reason }
+    }
+
+    #[must_use]
+    pub const fn tx(&self) -> &Tx {
+        &self.tx
+    }
+
+    #[must_use]
+    pub const fn reason(&self) -> &RejectionReason {
+        &self.reason
+    }
+}
+
+/// The outcome of processing one epoch of proposed txs
+#[derive(Clone, Debug)]
+pub struct EpochReport {
+    /// Accepted txs, in the order they were applied to the `UtxoPool`
+    accepted: Vec<Tx>,
+    /// Rejected txs, in the order they were proposed
+    rejected: Vec<RejectedTx>,
+    /// `Utxo`s added to the `UtxoPool` by the accepted txs
+    created: Vec<Utxo>,
+    /// `Utxo`s removed from the `UtxoPool` by the accepted txs
+    spent: Vec<Utxo>,
+    /// Sum of the fees paid by the accepted txs
+    total_fees: Decimal,
+}
+
+impl EpochReport {
+    pub(crate) const fn new(
+        accepted: Vec<Tx>,
+        rejected: Vec<RejectedTx>,
+        created: Vec<Utxo>,
+        spent: Vec<Utxo>,
+        total_fees: Decimal,
+    ) -> Self {
+        Self {
+            accepted,
+            rejected,
+            created,
+            spent,
+            total_fees,
+        }
+    }
+
+    #[must_use]
+    pub fn accepted(&self) -> &[Tx] {
+        &self.accepted
+    }
+
+    #[must_use]
+    pub fn rejected(&self) -> &[RejectedTx] {
+        &self.rejected
+    }
+
+    #[must_use]
+    pub fn created(&self) -> &[Utxo] {
+        &self.created
+    }
+
+    #[must_use]
+    pub fn spent(&self) -> &[Utxo] {
+        &self.spent
+    }
+
+    #[must_use]
+    pub const fn total_fees(&self) -> Decimal {
+        self.total_fees
+    }
+
+    /// Consumes the report, returning the accepted txs
+    #[must_use]
+    pub fn into_accepted(self) -> Vec<Tx> {
+        self.accepted
+    }
+}
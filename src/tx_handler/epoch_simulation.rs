@@ -0,0 +1,42 @@
+use crate::{tx_handler::EpochReport, UtxoDelta};
+
+/// The would-be outcome of an epoch, computed without touching the handler's `UtxoPool`
+#[derive(Clone, Debug)]
+pub struct EpochSimulation {
+    report: EpochReport,
+    /// Changes committing the simulation would make to the pool
+    delta: UtxoDelta,
+    /// Version of the pool the simulation ran against
+    base_version: u64,
+}
+
+impl EpochSimulation {
+    pub(crate) const fn new(report: EpochReport, delta: UtxoDelta, base_version: u64) -> Self {
+        Self {
+            report,
+            delta,
+            base_version,
+        }
+    }
+
+    #[must_use]
+    pub const fn report(&self) -> &EpochReport {
+        &self.report
+    }
+
+    #[must_use]
+    pub const fn delta(&self) -> &UtxoDelta {
+        &self.delta
+    }
+
+    #[must_use]
+    pub const fn base_version(&self) -> u64 {
+        self.base_version
+    }
+
+    /// Consumes the simulation, returning its report
+    #[must_use]
+    pub fn into_report(self) -> EpochReport {
+        self.report
+    }
+}
@@ -1,6 +1,16 @@
-use crate::tx::{InputTx, TxHash, TxIdx};
+use crate::{
+    tx::{InputTx, TxHash, TxIdx},
+    Error, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// An unspent tx output, identified by the hash of the tx which created it and the output's index
+/// in that tx.  Displays, and parses from, the outpoint form `txhash:idx`.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Utxo {
     /// Index of the corresponding output in said tx
     tx_idx: TxIdx,
@@ -39,9 +49,37 @@ impl PartialEq<InputTx> for Utxo {
             | InputTx::Signed {
                 output_idx,
                 prev_tx_hash,
-                signature: _,
+                ..
             } => self.tx_idx == *output_idx && self.tx_hash == *prev_tx_hash,
-            _ => false,
+            InputTx::Genesis => false,
+        }
+    }
+}
+
+impl Display for Utxo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.tx_hash, self.tx_idx)
+    }
+}
+
+impl FromStr for Utxo {
+    type Err = Error;
+
+    /// Parses a `Utxo` from `txhash:idx`: 64 hex digits and a decimal index
+    fn from_str(outpoint: &str) -> Result<Self> {
+        let invalid = || Error::ParseUtxo(outpoint.to_string());
+        let mut parts = outpoint.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            // `u64::from_str()` accepts a leading `+`, which is not part of the outpoint form
+            (Some(tx_hash), Some(tx_idx))
+                if !tx_idx.is_empty() && tx_idx.chars().all(|digit| digit.is_ascii_digit()) =>
+            {
+                Ok(Self::new(
+                    tx_hash.parse().map_err(|_| invalid())?,
+                    TxIdx::new(tx_idx.parse().map_err(|_| invalid())?),
+                ))
+            }
+            _ => Err(invalid()),
         }
     }
 }
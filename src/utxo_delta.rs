@@ -0,0 +1,50 @@
+use crate::{tx::OutputTx, Utxo};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A set of changes to a `UtxoPool`
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UtxoDelta {
+    /// `Utxo`s added to the pool, with their corresponding tx outputs
+    added: Vec<(Utxo, OutputTx)>,
+    /// `Utxo`s removed from the pool, with the tx outputs they mapped to
+    removed: Vec<(Utxo, OutputTx)>,
+}
+
+impl UtxoDelta {
+    pub(crate) const fn new(added: Vec<(Utxo, OutputTx)>, removed: Vec<(Utxo, OutputTx)>) -> Self {
+        Self { added, removed }
+    }
+
+    /// Creates a delta from unordered changes, ordering each list by (`TxHash`, `TxIdx`) so equal
+    /// deltas compare and serialize equal
+    pub(crate) fn from_unsorted(
+        added: HashMap<Utxo, OutputTx>,
+        removed: HashMap<Utxo, OutputTx>,
+    ) -> Self {
+        let sorted = |entries: HashMap<Utxo, OutputTx>| {
+            let mut entries = entries.into_iter().collect::<Vec<_>>();
+            entries.sort_by(|(lhs, _), (rhs, _)| {
+                (lhs.tx_hash(), lhs.tx_idx()).cmp(&(rhs.tx_hash(), rhs.tx_idx()))
+            });
+            entries
+        };
+        Self::new(sorted(added), sorted(removed))
+    }
+
+    #[must_use]
+    pub fn added(&self) -> &[(Utxo, OutputTx)] {
+        &self.added
+    }
+
+    #[must_use]
+    pub fn removed(&self) -> &[(Utxo, OutputTx)] {
+        &self.removed
+    }
+
+    /// Return true if applying the delta would leave a pool unchanged
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
@@ -1,46 +1,442 @@
-use crate::{tx::OutputTx, Error, Result, Utxo};
-use std::collections::HashMap;
+mod commitment;
+mod entry;
+mod journal;
+mod overlay;
+mod sparse_merkle_tree;
+mod supply;
 
+pub use commitment::UtxoSetCommitment;
+pub use entry::{OccupiedUtxoEntry, UtxoEntry, VacantUtxoEntry};
+pub use journal::Checkpoint;
+use journal::UndoEntry;
+pub(crate) use overlay::UtxoPoolOverlay;
+use sparse_merkle_tree::SparseMerkleTree;
+pub use sparse_merkle_tree::{verify_proof, UtxoMerkleRoot, UtxoProof};
+use supply::Supply;
+
+use crate::{
+    consts::msg,
+    tx::{OutputTx, PublicKey},
+    Error, Result, Tx, Utxo, UtxoDelta,
+};
+use curve25519_dalek::{ristretto::RistrettoPoint, traits::Identity};
+use rust_decimal::Decimal;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
+
+/// Read access to a collection of UTXOs, shared by `UtxoPool` and overlays on it
+pub(crate) trait UtxoView {
+    /// Return the tx output corresponding to `Utxo` or `None` if `Utxo` is not in the collection
+    fn tx_output(&self, utxo: &Utxo) -> Option<&OutputTx>;
+}
+
+/// The collection of unspent transaction outputs.
+///
+/// The pool's collections are persistent (structurally shared) data structures, so `clone()` takes constant time and changes made to
+/// either copy afterwards duplicate only the parts of the collections they touch.
 #[derive(Clone, Debug)]
 pub struct UtxoPool {
     /// The current collection of UTXOs, with each one mapped to its corresponding tx
     /// output
-    hash_map: HashMap<Utxo, OutputTx>,
+    hash_map: im::HashMap<Utxo, OutputTx>,
+    /// Secondary index from each recipient address to the UTXOs paying it
+    by_address: im::HashMap<PublicKey, im::HashSet<Utxo>>,
+    /// Sum of the values of all UTXOs in the pool
+    supply: Supply,
+    /// Sum of the commitment elements of all entries in the pool
+    commitment: RistrettoPoint,
+    /// Authenticated structure over the pool's entries, for inclusion and exclusion proofs
+    merkle_tree: SparseMerkleTree,
+    /// Incremented on every change to the pool; used to detect concurrent modification
+    version: u64,
+    /// How to undo each change made since the first open `Checkpoint`, oldest first; `None`
+    /// while no checkpoint is open
+    journal: Option<im::Vector<UndoEntry>>,
+    /// Checkpoints which can still be rolled back to, oldest first
+    checkpoints: im::Vector<Checkpoint>,
+    /// Id of the next `Checkpoint` taken.  Ids are never reused, so a checkpoint invalidated by
+    /// `commit()` or by rolling back past it is never mistaken for a live one.
+    next_checkpoint_id: u64,
 }
 
 impl UtxoPool {
     /// Creates a new empty `UtxoPool`
+    #[must_use]
     pub fn new() -> Self {
         Self {
-            hash_map: HashMap::new(),
+            hash_map: im::HashMap::new(),
+            by_address: im::HashMap::new(),
+            supply: Supply::default(),
+            commitment: RistrettoPoint::identity(),
+            merkle_tree: SparseMerkleTree::default(),
+            version: 0,
+            journal: None,
+            checkpoints: im::Vector::new(),
+            next_checkpoint_id: 0,
         }
     }
 
-    /// Adds a mapping from `Utxo` to a `Transaction::Output` to the pool
+    /// Adds a mapping from `Utxo` to a `Transaction::Output` to the pool.  Fails, leaving the
+    /// pool unchanged, if `Utxo` is already in the pool.
     pub fn add_utxo(&mut self, utxo: Utxo, tx_out: OutputTx) -> Result<&mut Self> {
-        self.hash_map
-            .insert(utxo.clone(), tx_out)
-            .ok_or_else(|| Error::AddDuplicateUtxoAttempted(utxo))?;
+        self.try_insert(utxo, tx_out)?;
         Ok(self)
     }
 
+    /// Adds a mapping from `Utxo` to `tx_out` to the pool, returning a reference to `tx_out`.
+    /// Fails, leaving the pool unchanged, if `Utxo` is already in the pool or the pool's total
+    /// supply would overflow.
+    pub fn try_insert(&mut self, utxo: Utxo, tx_out: OutputTx) -> Result<&OutputTx> {
+        match self.entry(utxo) {
+            UtxoEntry::Occupied(entry) => Err(Error::AddDuplicateUtxoAttempted {
+                utxo: entry.key().clone(),
+                existing: entry.get().clone(),
+                attempted: tx_out,
+            }),
+            UtxoEntry::Vacant(entry) => entry.insert(tx_out),
+        }
+    }
+
+    /// Maps `Utxo` to `tx_out`, returning the tx output `Utxo` previously mapped to, if any.  Fails,
+    /// leaving the pool unchanged, if the pool's total supply would overflow.
+    pub fn insert_or_replace(&mut self, utxo: Utxo, tx_out: OutputTx) -> Result<Option<OutputTx>> {
+        self.insert_entry(utxo, tx_out)
+    }
+
+    /// Return the entry for `Utxo`, for in-place inspection and modification
+    pub fn entry(&mut self, utxo: Utxo) -> UtxoEntry<'_> {
+        UtxoEntry::new(self, utxo)
+    }
+
     /// Removes the `Utxo` from the pool
     pub fn remove_utxo(&mut self, utxo: &Utxo) -> Option<OutputTx> {
-        self.hash_map.remove(utxo)
+        self.remove_entry(utxo)
+    }
+
+    /// Spends the `Utxo`s claimed by `tx` and adds one `Utxo` per output of `tx` to the pool
+    pub(crate) fn apply_tx(&mut self, tx: &Tx) {
+        for utxo in tx.spent_utxos() {
+            self.remove_entry(&utxo);
+        }
+        // A valid tx pays out no more than the `Utxo`s it spends, so the supply cannot overflow
+        for (utxo, tx_out) in tx.created_utxos().zip(tx.outputs().cloned()) {
+            self.insert_entry(utxo, tx_out)
+                .unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL));
+        }
+    }
+
+    /// Removes the `Utxo`s `delta` removes from the pool, then adds the `Utxo`s it adds
+    pub(crate) fn apply_delta(&mut self, delta: &UtxoDelta) {
+        for (utxo, _) in delta.removed() {
+            self.remove_entry(utxo);
+        }
+        // `check_delta()` has established the supply does not overflow
+        for (utxo, tx_out) in delta.added() {
+            self.insert_entry(utxo.clone(), tx_out.clone())
+                .unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL));
+        }
+    }
+
+    /// Return the changes which turn this pool into `other`.  A `Utxo` mapped to different tx
+    /// outputs in the two pools is both removed and added.
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> UtxoDelta {
+        let changed = |from: &Self, to: &Self| {
+            from.hash_map
+                .iter()
+                .filter(|(utxo, tx_out)| to.hash_map.get(utxo) != Some(tx_out))
+                .map(|(utxo, tx_out)| (utxo.clone(), tx_out.clone()))
+                .collect::<HashMap<_, _>>()
+        };
+        UtxoDelta::from_unsorted(changed(other, self), changed(self, other))
+    }
+
+    /// Applies `delta` to the pool.  Fails, leaving the pool unchanged, unless every `Utxo` `delta`
+    /// removes is in the pool mapped to the recorded tx output and every `Utxo` it adds is not in
+    /// the pool once the removals are made, and the pool's total supply does not overflow.
+    pub fn apply(&mut self, delta: &UtxoDelta) -> Result<&mut Self> {
+        self.check_delta(delta)?;
+        self.apply_delta(delta);
+        Ok(self)
+    }
+
+    /// Checks the preconditions `apply()` places on `delta`
+    pub(crate) fn check_delta(&self, delta: &UtxoDelta) -> Result<()> {
+        let mut removed = HashSet::new();
+        let mut supply = self.supply;
+        for (utxo, tx_out) in delta.removed() {
+            if self.hash_map.get(utxo) != Some(tx_out) || !removed.insert(utxo) {
+                Err(Error::DeltaRemovesMissingUtxo(utxo.clone()))?;
+            }
+            supply = supply.remove(tx_out.value());
+        }
+        let mut added = HashSet::new();
+        for (utxo, tx_out) in delta.added() {
+            if (self.hash_map.contains_key(utxo) && !removed.contains(utxo)) || !added.insert(utxo)
+            {
+                Err(Error::DeltaAddsExistingUtxo(utxo.clone()))?;
+            }
+            supply = supply
+                .checked_add(tx_out.value())
+                .ok_or_else(|| Error::SupplyOverflow(utxo.clone()))?;
+        }
+        Ok(())
     }
 
     /// Return the tx output corresponding to `Utxo` or `None` if `Utxo` is not in the pool
+    #[must_use]
     pub fn tx_output(&self, utxo: &Utxo) -> Option<&OutputTx> {
         self.hash_map.get(utxo)
     }
 
     /// Return true if `Utxo` is in the pool and false otherwise
+    #[must_use]
     pub fn contains(&self, utxo: &Utxo) -> bool {
         self.hash_map.contains_key(utxo)
     }
 
     /// Returns a `Vec` of all UTXO's in the pool
+    #[must_use]
     pub fn all_utxos(&self) -> Vec<Utxo> {
-        self.hash_map.iter().map(|(k, v)| k).cloned().collect()
+        self.hash_map.keys().cloned().collect()
+    }
+
+    /// Returns a `Vec` of the UTXOs in the pool paying `address`
+    #[must_use]
+    pub fn utxos_for(&self, address: &PublicKey) -> Vec<Utxo> {
+        self.by_address
+            .get(address)
+            .map_or_else(Vec::new, |utxos| utxos.iter().cloned().collect())
+    }
+
+    /// Return the sum of the values of the UTXOs in the pool paying `address`
+    #[must_use]
+    pub fn balance_of(&self, address: &PublicKey) -> Decimal {
+        // Any subset of the pool's values sums without overflow (see `Supply`)
+        self.by_address
+            .get(address)
+            .into_iter()
+            .flatten()
+            .filter_map(|utxo| self.hash_map.get(utxo))
+            .try_fold(Supply::default(), |supply, tx_out| {
+                supply.checked_add(tx_out.value())
+            })
+            .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL))
+            .total()
+    }
+
+    /// Return the sum of the values of all UTXOs in the pool
+    #[must_use]
+    pub fn total_supply(&self) -> Decimal {
+        self.supply.total()
+    }
+
+    /// Return a commitment to the pool's contents, for comparing pools without exchanging their
+    /// `Utxo`s
+    #[must_use]
+    pub fn commitment(&self) -> UtxoSetCommitment {
+        UtxoSetCommitment::new(&self.commitment)
+    }
+
+    /// Return the root of the sparse Merkle tree over the pool's entries, which is kept up to date
+    /// as the pool changes
+    #[must_use]
+    pub const fn merkle_root(&self) -> UtxoMerkleRoot {
+        self.merkle_tree.root()
+    }
+
+    /// Return a proof, checkable against `merkle_root()` with `verify_proof()`, that `Utxo` is in
+    /// the pool, or `None` if it is not
+    #[must_use]
+    pub fn prove_inclusion(&self, utxo: &Utxo) -> Option<UtxoProof> {
+        self.hash_map.get(utxo).map(|tx_out| {
+            let (siblings, _) = self.merkle_tree.path(utxo);
+            UtxoProof::Inclusion {
+                utxo: utxo.clone(),
+                tx_out: tx_out.clone(),
+                siblings,
+            }
+        })
+    }
+
+    /// Return a proof, checkable against `merkle_root()` with `verify_proof()`, that `Utxo` is not
+    /// in the pool, or `None` if it is
+    #[must_use]
+    pub fn prove_exclusion(&self, utxo: &Utxo) -> Option<UtxoProof> {
+        match self.hash_map.contains_key(utxo) {
+            true => None,
+            false => {
+                let (siblings, neighbour) = self.merkle_tree.path(utxo);
+                Some(UtxoProof::Exclusion {
+                    utxo: utxo.clone(),
+                    neighbour,
+                    siblings,
+                })
+            }
+        }
+    }
+
+    /// Return the pool's version, which changes whenever the pool does
+    #[must_use]
+    pub const fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Marks the current state of the pool so it can later be restored with `rollback_to()`.
+    /// From the first checkpoint until `commit()` the pool journals how to undo each change.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        let journal_len = self.journal.get_or_insert_with(im::Vector::new).len();
+        let checkpoint = Checkpoint::new(self.next_checkpoint_id, journal_len);
+        self.next_checkpoint_id = self.next_checkpoint_id.wrapping_add(1);
+        self.checkpoints.push_back(checkpoint);
+        checkpoint
+    }
+
+    /// Undoes every change made to the pool since `checkpoint` was taken, invalidating every
+    /// checkpoint taken after it.  `checkpoint` itself stays valid.  Fails if the pool has been
+    /// committed or rolled back past `checkpoint` since.
+    pub fn rollback_to(&mut self, checkpoint: Checkpoint) -> Result<&mut Self> {
+        let position = self
+            .checkpoints
+            .index_of(&checkpoint)
+            .ok_or(Error::InvalidCheckpoint)?;
+        // Later checkpoints mark states the rollback discards
+        self.checkpoints.truncate(position.saturating_add(1));
+        // The journal is detached while undoing so the undo operations are not themselves journaled
+        let mut journal = self
+            .journal
+            .take()
+            .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL));
+
+        let undone = journal.split_off(checkpoint.journal_len());
+        for entry in undone.into_iter().rev() {
+            match entry {
+                UndoEntry::Added(utxo) => {
+                    self.remove_entry(&utxo);
+                }
+                // Undoing restores an earlier state of the pool, whose supply did not overflow
+                UndoEntry::Removed(utxo, tx_out) => {
+                    self.insert_entry(utxo, tx_out)
+                        .unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL));
+                }
+            }
+        }
+        self.journal = Some(journal);
+        Ok(self)
+    }
+
+    /// Makes all changes since the first open checkpoint permanent, discarding the undo journal
+    /// and invalidating all outstanding checkpoints
+    pub fn commit(&mut self) -> &mut Self {
+        self.journal = None;
+        self.checkpoints.clear();
+        self
+    }
+
+    /// Every insertion into the pool goes through here.  Fails, leaving the pool unchanged, if the
+    /// total supply would overflow.
+    fn insert_entry(&mut self, utxo: Utxo, tx_out: OutputTx) -> Result<Option<OutputTx>> {
+        let supply = self
+            .hash_map
+            .get(&utxo)
+            .map_or(self.supply, |replaced| self.supply.remove(replaced.value()))
+            .checked_add(tx_out.value())
+            .ok_or_else(|| Error::SupplyOverflow(utxo.clone()))?;
+
+        self.version = self.version.wrapping_add(1);
+        let replaced = self.hash_map.remove(&utxo);
+        if let Some(replaced) = &replaced {
+            self.index_remove(&utxo, replaced);
+        }
+        self.index_insert(&utxo, &tx_out);
+        self.hash_map.insert(utxo.clone(), tx_out);
+        self.supply = supply;
+        if let Some(journal) = &mut self.journal {
+            journal.push_back(match &replaced {
+                Some(replaced) => UndoEntry::Removed(utxo, replaced.clone()),
+                None => UndoEntry::Added(utxo),
+            });
+        }
+        Ok(replaced)
+    }
+
+    /// Every removal from the pool goes through here
+    fn remove_entry(&mut self, utxo: &Utxo) -> Option<OutputTx> {
+        let removed = self.hash_map.remove(utxo);
+        if let Some(tx_out) = &removed {
+            self.version = self.version.wrapping_add(1);
+            self.index_remove(utxo, tx_out);
+            self.supply = self.supply.remove(tx_out.value());
+            if let Some(journal) = &mut self.journal {
+                journal.push_back(UndoEntry::Removed(utxo.clone(), tx_out.clone()));
+            }
+        }
+        removed
+    }
+
+    /// Records `utxo` paying `tx_out` in the address index, commitment and Merkle tree
+    fn index_insert(&mut self, utxo: &Utxo, tx_out: &OutputTx) {
+        self.merkle_tree.insert(utxo, tx_out);
+        self.commitment += commitment::element(utxo, tx_out);
+        self.by_address
+            .entry(tx_out.address().clone())
+            .or_default()
+            .insert(utxo.clone());
+    }
+
+    /// Forgets `utxo` paying `tx_out` in the address index, commitment and Merkle tree
+    fn index_remove(&mut self, utxo: &Utxo, tx_out: &OutputTx) {
+        self.merkle_tree.remove(utxo);
+        self.commitment -= commitment::element(utxo, tx_out);
+        if let Some(utxos) = self.by_address.get_mut(tx_out.address()) {
+            utxos.remove(utxo);
+            if utxos.is_empty() {
+                self.by_address.remove(tx_out.address());
+            }
+        }
+    }
+}
+
+impl Default for UtxoPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UtxoView for UtxoPool {
+    fn tx_output(&self, utxo: &Utxo) -> Option<&OutputTx> {
+        Self::tx_output(self, utxo)
+    }
+}
+
+/// Serializes the pool's entries ordered by (`TxHash`, `TxIdx`).  The version and any undo journal
+/// are not serialized.
+impl Serialize for UtxoPool {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut entries = self.hash_map.iter().collect::<Vec<_>>();
+        entries.sort_by(|(lhs, _), (rhs, _)| {
+            (lhs.tx_hash(), lhs.tx_idx()).cmp(&(rhs.tx_hash(), rhs.tx_idx()))
+        });
+        serializer.collect_seq(entries)
+    }
+}
+
+/// Rebuilds a pool from its serialized entries, rejecting any `Utxo` which appears more than once
+impl<'de> Deserialize<'de> for UtxoPool {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let entries = Vec::<(Utxo, OutputTx)>::deserialize(deserializer)?;
+        let mut utxo_pool = Self::new();
+        for (utxo, tx_out) in entries {
+            utxo_pool
+                .try_insert(utxo, tx_out)
+                .map_err(de::Error::custom)?;
+        }
+        Ok(utxo_pool)
     }
 }
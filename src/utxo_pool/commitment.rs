@@ -0,0 +1,75 @@
+use crate::{
+    consts::{self, msg},
+    tx::{Address, OutputTx},
+    Error, Result, Utxo,
+};
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use sha2::Sha512;
+use std::{
+    convert::TryFrom,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// Commitment to the full contents of a `UtxoPool`.
+///
+/// Equal pools have equal commitments and unequal pools have different commitments with overwhelming probability, irrespective of the
+/// order in which either pool's `Utxo`s were added and removed
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub struct UtxoSetCommitment([u8; 32]);
+
+impl UtxoSetCommitment {
+    pub(crate) fn new(sum: &RistrettoPoint) -> Self {
+        Self(sum.compress().to_bytes())
+    }
+
+    /// Constructs a `UtxoSetCommitment` from its 32-byte encoding, as received from a peer.  Fails
+    /// if `bytes` does not encode a point of the Ristretto group, which no pool can commit to.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        <[u8; 32]>::try_from(bytes)
+            .ok()
+            .filter(|bytes| CompressedRistretto(*bytes).decompress().is_some())
+            .map(Self)
+            .ok_or(Error::MalformedUtxoSetCommitment)
+    }
+
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// Accepts only the encoding of a point of the Ristretto group
+impl<'de> Deserialize<'de> for UtxoSetCommitment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = <[u8; 32]>::deserialize(deserializer)?;
+        Self::from_bytes(&bytes).map_err(de::Error::custom)
+    }
+}
+
+impl Display for UtxoSetCommitment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// Maps the pool entry `utxo` -> `tx_out` to a point on the Ristretto group.  The pool's
+/// commitment is the sum of the points of its entries (an elliptic curve multiset hash), so adding
+/// or removing an entry updates it in constant time.
+pub(crate) fn element(utxo: &Utxo, tx_out: &OutputTx) -> RistrettoPoint {
+    let mut bytes = consts::UTXO_SET_COMMITMENT_DOMAIN.as_bytes().to_vec();
+    bytes.extend_from_slice(
+        &u64::try_from(utxo.tx_hash().as_ref().len())
+            .unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL))
+            .to_le_bytes(),
+    );
+    bytes.extend_from_slice(utxo.tx_hash().as_ref());
+    bytes.extend_from_slice(&utxo.tx_idx().0.to_le_bytes());
+    // Equal values may differ in scale; commit to a single representation of each
+    bytes.extend_from_slice(&tx_out.value().normalize().serialize());
+    bytes.extend_from_slice(tx_out.address().as_bytes());
+    RistrettoPoint::hash_from_bytes::<Sha512>(&bytes)
+}
@@ -0,0 +1,111 @@
+use crate::{consts::msg, tx::OutputTx, Result, Utxo, UtxoPool};
+
+/// A view into a single `Utxo` of a `UtxoPool`, which may or may not be present
+#[derive(Debug)]
+pub enum UtxoEntry<'a> {
+    Occupied(OccupiedUtxoEntry<'a>),
+    Vacant(VacantUtxoEntry<'a>),
+}
+
+impl<'a> UtxoEntry<'a> {
+    pub(crate) fn new(pool: &'a mut UtxoPool, utxo: Utxo) -> Self {
+        match pool.contains(&utxo) {
+            true => Self::Occupied(OccupiedUtxoEntry { pool, utxo }),
+            false => Self::Vacant(VacantUtxoEntry { pool, utxo }),
+        }
+    }
+
+    #[must_use]
+    pub const fn key(&self) -> &Utxo {
+        match self {
+            Self::Occupied(entry) => entry.key(),
+            Self::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Inserts `tx_out` if the `Utxo` is not in the pool, then returns its tx output.  Fails,
+    /// leaving the pool unchanged, if the pool's total supply would overflow.
+    pub fn or_insert(self, tx_out: OutputTx) -> Result<&'a OutputTx> {
+        self.or_insert_with(|| tx_out)
+    }
+
+    /// Inserts the result of `f` if the `Utxo` is not in the pool, then returns its tx output.
+    /// Fails, leaving the pool unchanged, if the pool's total supply would overflow.
+    pub fn or_insert_with<F: FnOnce() -> OutputTx>(self, f: F) -> Result<&'a OutputTx> {
+        match self {
+            Self::Occupied(entry) => Ok(entry.into_ref()),
+            Self::Vacant(entry) => entry.insert(f()),
+        }
+    }
+}
+
+/// A view into a `Utxo` which is present in a `UtxoPool`
+#[derive(Debug)]
+pub struct OccupiedUtxoEntry<'a> {
+    pool: &'a mut UtxoPool,
+    utxo: Utxo,
+}
+
+impl<'a> OccupiedUtxoEntry<'a> {
+    #[must_use]
+    pub const fn key(&self) -> &Utxo {
+        &self.utxo
+    }
+
+    /// Return the tx output the `Utxo` maps to
+    #[must_use]
+    pub fn get(&self) -> &OutputTx {
+        self.pool
+            .tx_output(&self.utxo)
+            .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL))
+    }
+
+    /// Converts the entry into a reference to the tx output the `Utxo` maps to
+    #[must_use]
+    pub fn into_ref(self) -> &'a OutputTx {
+        let pool: &'a UtxoPool = self.pool;
+        pool.tx_output(&self.utxo)
+            .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL))
+    }
+
+    /// Maps the `Utxo` to `tx_out` instead, returning the tx output it previously mapped to.
+    /// Fails, leaving the pool unchanged, if the pool's total supply would overflow.
+    pub fn replace(&mut self, tx_out: OutputTx) -> Result<OutputTx> {
+        Ok(self
+            .pool
+            .insert_entry(self.utxo.clone(), tx_out)?
+            .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL)))
+    }
+
+    /// Removes the `Utxo` from the pool, returning the tx output it mapped to
+    #[allow(clippy::must_use_candidate)]
+    pub fn remove(self) -> OutputTx {
+        self.pool
+            .remove_entry(&self.utxo)
+            .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL))
+    }
+}
+
+/// A view into a `Utxo` which is absent from a `UtxoPool`
+#[derive(Debug)]
+pub struct VacantUtxoEntry<'a> {
+    pool: &'a mut UtxoPool,
+    utxo: Utxo,
+}
+
+impl<'a> VacantUtxoEntry<'a> {
+    #[must_use]
+    pub const fn key(&self) -> &Utxo {
+        &self.utxo
+    }
+
+    /// Adds the `Utxo` to the pool mapped to `tx_out`, returning a reference to it.  Fails,
+    /// leaving the pool unchanged, if the pool's total supply would overflow.
+    pub fn insert(self, tx_out: OutputTx) -> Result<&'a OutputTx> {
+        self.pool.insert_entry(self.utxo.clone(), tx_out)?;
+        let pool: &'a UtxoPool = self.pool;
+        Ok(pool
+            .tx_output(&self.utxo)
+            .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL)))
+    }
+}
@@ -0,0 +1,29 @@
+use crate::{tx::OutputTx, Utxo};
+
+/// How to undo one change to a `UtxoPool`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum UndoEntry {
+    /// The `Utxo` was added to the pool; undo by removing it
+    Added(Utxo),
+    /// The `Utxo` was removed from (or replaced in) the pool; undo by re-inserting its tx output
+    Removed(Utxo, OutputTx),
+}
+
+/// A point in a `UtxoPool`'s undo journal which the pool can be rolled back to
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Checkpoint {
+    /// Unique (per pool) id of the checkpoint
+    id: u64,
+    /// Length of the journal when the checkpoint was taken
+    journal_len: usize,
+}
+
+impl Checkpoint {
+    pub(crate) const fn new(id: u64, journal_len: usize) -> Self {
+        Self { id, journal_len }
+    }
+
+    pub(crate) const fn journal_len(self) -> usize {
+        self.journal_len
+    }
+}
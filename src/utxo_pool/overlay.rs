@@ -0,0 +1,55 @@
+use crate::{tx::OutputTx, utxo_pool::UtxoView, Tx, Utxo, UtxoDelta, UtxoStore};
+use std::collections::HashMap;
+
+/// A scratch layer over a `UtxoStore` which records changes without touching the pool itself
+#[derive(Debug)]
+pub(crate) struct UtxoPoolOverlay<'a, S: UtxoStore + ?Sized> {
+    base: &'a S,
+    /// `Utxo`s added on top of `base`
+    added: HashMap<Utxo, OutputTx>,
+    /// `Utxo`s of `base` which have been removed, with the tx outputs they mapped to
+    removed: HashMap<Utxo, OutputTx>,
+}
+
+impl<'a, S: UtxoStore + ?Sized> UtxoPoolOverlay<'a, S> {
+    pub(crate) fn new(base: &'a S) -> Self {
+        Self {
+            base,
+            added: HashMap::new(),
+            removed: HashMap::new(),
+        }
+    }
+
+    /// Spends the `Utxo`s claimed by `tx` and adds one `Utxo` per output of `tx` to the overlay
+    pub(crate) fn apply_tx(&mut self, tx: &Tx) {
+        for utxo in tx.spent_utxos() {
+            // A `Utxo` created and spent within the overlay never reaches `base`
+            if self.added.remove(&utxo).is_none() {
+                if let Some(tx_out) = self.base.tx_output(&utxo) {
+                    self.removed.insert(utxo, tx_out.clone());
+                }
+            }
+        }
+        self.add_tx_outputs(tx);
+    }
+
+    /// Adds one `Utxo` per output of `tx` to the overlay without spending its inputs
+    pub(crate) fn add_tx_outputs(&mut self, tx: &Tx) {
+        self.added
+            .extend(tx.created_utxos().zip(tx.outputs().cloned()));
+    }
+
+    /// Return the net changes the overlay makes to its base store
+    pub(crate) fn into_delta(self) -> UtxoDelta {
+        UtxoDelta::from_unsorted(self.added, self.removed)
+    }
+}
+
+impl<S: UtxoStore + ?Sized> UtxoView for UtxoPoolOverlay<'_, S> {
+    fn tx_output(&self, utxo: &Utxo) -> Option<&OutputTx> {
+        match self.removed.contains_key(utxo) {
+            true => None,
+            false => self.added.get(utxo).or_else(|| self.base.tx_output(utxo)),
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use crate::{
+    consts::msg,
+    error::PanicError,
+    tx::{Address, OutputTx},
+    Error, Result, Utxo,
+};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    convert::TryFrom,
+    fmt::{Display, Formatter, Result as FmtResult},
+    sync::Arc,
+};
+
+/// Hash of an empty subtree
+const EMPTY: [u8; 32] = [0; 32];
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+const KEY_BITS: usize = 256;
+const BIT_MASKS: [u8; 8] = [0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x01];
+
+/// A leaf's key and value hash
+type Leaf = ([u8; 32], [u8; 32]);
+
+/// Root hash of the sparse Merkle tree over a `UtxoPool`
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct UtxoMerkleRoot([u8; 32]);
+
+impl UtxoMerkleRoot {
+    /// Constructs a `UtxoMerkleRoot` from its 32 bytes, as received from a peer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        <[u8; 32]>::try_from(bytes)
+            .map(Self)
+            .map_err(|_| Error::UtxoMerkleRootLength(bytes.len()))
+    }
+
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl Display for UtxoMerkleRoot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// Proof that a `Utxo` is or is not in a `UtxoPool` with a given `UtxoMerkleRoot`.  `siblings`
+/// holds the hashes of the subtrees alongside the path to the `Utxo`'s key, root first.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum UtxoProof {
+    /// `utxo` maps to `tx_out` in the pool
+    Inclusion {
+        utxo: Utxo,
+        tx_out: OutputTx,
+        siblings: Vec<[u8; 32]>,
+    },
+    /// `utxo` is not in the pool: the path to its key ends either at an empty subtree or at a
+    /// subtree holding only the leaf `neighbour` (as key, value hash) for another `Utxo`
+    Exclusion {
+        utxo: Utxo,
+        neighbour: Option<([u8; 32], [u8; 32])>,
+        siblings: Vec<[u8; 32]>,
+    },
+}
+
+impl UtxoProof {
+    /// Decodes a proof encoded by `to_bytes()`, as received from a peer.  A proof which decodes
+    /// still has to be checked with `verify_proof()`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        encoding()
+            .deserialize(bytes)
+            .map_err(|_| Error::MalformedUtxoProof)
+    }
+
+    /// Return the bincode encoding of the proof
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encoding()
+            .serialize(self)
+            .unwrap_or_else(|err| panic!("{}", PanicError::Serialization(err)))
+    }
+
+    /// Return the `Utxo` the proof is about
+    #[must_use]
+    pub const fn utxo(&self) -> &Utxo {
+        match self {
+            Self::Inclusion { utxo, .. } | Self::Exclusion { utxo, .. } => utxo,
+        }
+    }
+}
+
+/// Sparse Merkle tree with one leaf per pool entry, at the path given by the SHA-256 of its
+/// `Utxo`.  Empty subtrees hash to zero and a subtree holding a single leaf hashes to that leaf, so
+/// only the populated part of the 256-level tree is ever hashed.
+///
+/// The tree is stored path-compressed: each `Node::Branch` sits at the depth where its leaves'
+/// keys first differ and caches its hash, so an insertion or removal rehashes only the nodes on
+/// its path.  Nodes are shared between clones, so cloning a pool does not copy the tree.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SparseMerkleTree {
+    root: Option<Arc<Node>>,
+    /// Hash of `root` as the subtree at depth 0
+    root_hash: [u8; 32],
+}
+
+#[derive(Debug)]
+enum Node {
+    Leaf {
+        key: [u8; 32],
+        value_hash: [u8; 32],
+        hash: [u8; 32],
+    },
+    /// Two non-empty subtrees whose keys share their first `depth` bits and differ at bit `depth`
+    Branch {
+        depth: usize,
+        /// Key of a leaf below the branch, from which the shared bits are read
+        key: [u8; 32],
+        left: Arc<Self>,
+        right: Arc<Self>,
+        /// Hashes of `left` and `right` as the subtrees at depth `depth + 1`
+        left_hash: [u8; 32],
+        right_hash: [u8; 32],
+        hash: [u8; 32],
+    },
+}
+
+impl SparseMerkleTree {
+    pub(crate) fn insert(&mut self, utxo: &Utxo, tx_out: &OutputTx) {
+        let key = key(utxo);
+        let leaf = Arc::new(Node::leaf(key, value_hash(tx_out)));
+        self.set_root(Some(match &self.root {
+            Some(root) => root.insert(leaf),
+            None => leaf,
+        }));
+    }
+
+    pub(crate) fn remove(&mut self, utxo: &Utxo) {
+        if let Some(root) = self.root.as_ref().and_then(|root| root.remove(&key(utxo))) {
+            self.set_root(root);
+        }
+    }
+
+    /// Return the root hash, which is cached
+    pub(crate) const fn root(&self) -> UtxoMerkleRoot {
+        UtxoMerkleRoot(self.root_hash)
+    }
+
+    /// Return the sibling hashes along the path to `utxo`'s key (root first) and the leaf the path
+    /// ends at, if any
+    pub(crate) fn path(&self, utxo: &Utxo) -> (Vec<[u8; 32]>, Option<Leaf>) {
+        let key = key(utxo);
+        let mut siblings = Vec::new();
+        let Some(mut node) = self.root.as_ref() else {
+            return (siblings, None);
+        };
+        let mut from_depth = 0;
+        loop {
+            match &**node {
+                Node::Leaf {
+                    key: leaf_key,
+                    value_hash,
+                    ..
+                } => return (siblings, Some((*leaf_key, *value_hash))),
+                Node::Branch {
+                    depth,
+                    key: branch_key,
+                    left,
+                    right,
+                    left_hash,
+                    right_hash,
+                    ..
+                } => {
+                    // Above the branch every leaf lies on one side, so the path either follows
+                    // them with an empty sibling or leaves them for an empty subtree
+                    for level in from_depth..*depth {
+                        match bit(&key, level) == bit(branch_key, level) {
+                            true => siblings.push(EMPTY),
+                            false => {
+                                siblings.push(node.hash_from(level.saturating_add(1)));
+                                return (siblings, None);
+                            }
+                        }
+                    }
+                    let (sibling, next) = match bit(&key, *depth) {
+                        true => (left_hash, right),
+                        false => (right_hash, left),
+                    };
+                    siblings.push(*sibling);
+                    node = next;
+                    from_depth = depth.saturating_add(1);
+                }
+            }
+        }
+    }
+
+    fn set_root(&mut self, root: Option<Arc<Node>>) {
+        self.root_hash = root.as_ref().map_or(EMPTY, |root| root.hash_from(0));
+        self.root = root;
+    }
+}
+
+impl Node {
+    fn leaf(key: [u8; 32], value_hash: [u8; 32]) -> Self {
+        Self::Leaf {
+            key,
+            value_hash,
+            hash: leaf_hash(&key, &value_hash),
+        }
+    }
+
+    /// Return the branch at `depth` over `left` and `right`
+    fn branch(depth: usize, left: Arc<Self>, right: Arc<Self>) -> Self {
+        let left_hash = left.hash_from(depth.saturating_add(1));
+        let right_hash = right.hash_from(depth.saturating_add(1));
+        Self::Branch {
+            depth,
+            key: *left.key(),
+            left,
+            right,
+            left_hash,
+            right_hash,
+            hash: node_hash(&left_hash, &right_hash),
+        }
+    }
+
+    /// Return the branch at `depth` over `lhs` and `rhs`, whose keys differ at bit `depth`
+    fn split_at(depth: usize, lhs: Arc<Self>, rhs: Arc<Self>) -> Self {
+        match bit(lhs.key(), depth) {
+            true => Self::branch(depth, rhs, lhs),
+            false => Self::branch(depth, lhs, rhs),
+        }
+    }
+
+    const fn key(&self) -> &[u8; 32] {
+        match self {
+            Self::Leaf { key, .. } | Self::Branch { key, .. } => key,
+        }
+    }
+
+    /// Return the hash of the node as the subtree at `from_depth`, which is no deeper than the
+    /// node.  Each level above a branch holds an empty subtree beside it.
+    fn hash_from(&self, from_depth: usize) -> [u8; 32] {
+        match self {
+            Self::Leaf { hash, .. } => *hash,
+            Self::Branch {
+                depth, key, hash, ..
+            } => (from_depth..*depth)
+                .rev()
+                .fold(*hash, |hash, level| match bit(key, level) {
+                    true => node_hash(&EMPTY, &hash),
+                    false => node_hash(&hash, &EMPTY),
+                }),
+        }
+    }
+
+    /// Return the subtree rooted at this node with `leaf` inserted, replacing any leaf with the
+    /// same key
+    fn insert(self: &Arc<Self>, leaf: Arc<Self>) -> Arc<Self> {
+        let key = *leaf.key();
+        let diverges_at = first_difference(&key, self.key());
+        match &**self {
+            Self::Leaf { .. } => match diverges_at {
+                Some(depth) => Arc::new(Self::split_at(depth, Arc::clone(self), leaf)),
+                None => leaf,
+            },
+            Self::Branch {
+                depth, left, right, ..
+            } => match diverges_at {
+                Some(diverges_at) if diverges_at < *depth => {
+                    Arc::new(Self::split_at(diverges_at, Arc::clone(self), leaf))
+                }
+                _ => Arc::new(match bit(&key, *depth) {
+                    true => Self::branch(*depth, Arc::clone(left), right.insert(leaf)),
+                    false => Self::branch(*depth, left.insert(leaf), Arc::clone(right)),
+                }),
+            },
+        }
+    }
+
+    /// Return the subtree rooted at this node with the leaf for `key` removed, or `None` if there
+    /// is no such leaf.  The inner `None` is the empty subtree.
+    #[allow(clippy::option_option)]
+    fn remove(self: &Arc<Self>, key: &[u8; 32]) -> Option<Option<Arc<Self>>> {
+        match &**self {
+            Self::Leaf { key: leaf_key, .. } => match leaf_key == key {
+                true => Some(None),
+                false => None,
+            },
+            Self::Branch {
+                depth,
+                key: branch_key,
+                left,
+                right,
+                ..
+            } => {
+                if first_difference(key, branch_key).is_some_and(|level| level < *depth) {
+                    return None;
+                }
+                let (side, other) = match bit(key, *depth) {
+                    true => (right, left),
+                    false => (left, right),
+                };
+                // A branch left with one subtree collapses into it
+                Some(Some(side.remove(key)?.map_or_else(
+                    || Arc::clone(other),
+                    |side| {
+                        Arc::new(match bit(key, *depth) {
+                            true => Self::branch(*depth, Arc::clone(left), side),
+                            false => Self::branch(*depth, side, Arc::clone(right)),
+                        })
+                    },
+                )))
+            }
+        }
+    }
+}
+
+/// Checks `proof` against `root`, returning true if it proves what it claims
+#[must_use]
+pub fn verify_proof(root: &UtxoMerkleRoot, proof: &UtxoProof) -> bool {
+    let (key, bottom, siblings) = match proof {
+        UtxoProof::Inclusion {
+            utxo,
+            tx_out,
+            siblings,
+        } => {
+            let key = key(utxo);
+            (key, leaf_hash(&key, &value_hash(tx_out)), siblings)
+        }
+        UtxoProof::Exclusion {
+            utxo,
+            neighbour: None,
+            siblings,
+        } => (key(utxo), EMPTY, siblings),
+        UtxoProof::Exclusion {
+            utxo,
+            neighbour: Some((neighbour_key, neighbour_value_hash)),
+            siblings,
+        } => {
+            let key = key(utxo);
+            // The neighbour must be a different leaf on the path to `key`
+            let on_path = bits(neighbour_key)
+                .take(siblings.len())
+                .eq(bits(&key).take(siblings.len()));
+            match on_path && *neighbour_key != key {
+                true => (
+                    key,
+                    leaf_hash(neighbour_key, neighbour_value_hash),
+                    siblings,
+                ),
+                false => return false,
+            }
+        }
+    };
+    if siblings.len() > KEY_BITS {
+        return false;
+    }
+
+    let path = bits(&key).take(siblings.len()).collect::<Vec<_>>();
+    let computed = siblings
+        .iter()
+        .zip(path)
+        .rev()
+        .fold(bottom, |hash, (sibling, bit)| match bit {
+            true => node_hash(sibling, &hash),
+            false => node_hash(&hash, sibling),
+        });
+    computed == root.0
+}
+
+/// Return the bincode options proofs are encoded with: those of `bincode::serialize()`, rejecting
+/// trailing bytes
+fn encoding() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+}
+
+/// Return the tree key of `utxo`
+fn key(utxo: &Utxo) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(
+        u64::try_from(utxo.tx_hash().as_ref().len())
+            .unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL))
+            .to_le_bytes(),
+    );
+    hasher.update(utxo.tx_hash().as_ref());
+    hasher.update(utxo.tx_idx().0.to_le_bytes());
+    to_array(&hasher.finalize())
+}
+
+fn value_hash(tx_out: &OutputTx) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    // Equal values may differ in scale; hash a single representation of each
+    hasher.update(tx_out.value().normalize().serialize());
+    hasher.update(tx_out.address().as_bytes());
+    to_array(&hasher.finalize())
+}
+
+fn leaf_hash(key: &[u8; 32], value_hash: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(key);
+    hasher.update(value_hash);
+    to_array(&hasher.finalize())
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    to_array(&hasher.finalize())
+}
+
+fn to_array(digest: &[u8]) -> [u8; 32] {
+    <[u8; 32]>::try_from(digest).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL))
+}
+
+/// Return the bits of `key`, most significant first
+fn bits(key: &[u8; 32]) -> impl Iterator<Item = bool> + '_ {
+    key.iter()
+        .flat_map(|byte| BIT_MASKS.iter().map(move |mask| byte & mask != 0))
+}
+
+/// Return bit `level` of `key`, counting from the most significant
+fn bit(key: &[u8; 32], level: usize) -> bool {
+    bits(key).nth(level).unwrap_or(false)
+}
+
+/// Return the first bit at which `lhs` and `rhs` differ, or `None` if they are equal
+fn first_difference(lhs: &[u8; 32], rhs: &[u8; 32]) -> Option<usize> {
+    bits(lhs).zip(bits(rhs)).position(|(lhs, rhs)| lhs != rhs)
+}
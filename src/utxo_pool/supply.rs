@@ -0,0 +1,63 @@
+use crate::consts::msg;
+use rust_decimal::Decimal;
+
+/// Running total of the values of a pool's UTXOs.  Positive and negative values are summed
+/// separately and each sum is checked as values are added, so taking a value back out (which
+/// moves its sum towards zero) never overflows, nor does summing any subset of the values.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Supply {
+    positive: Decimal,
+    negative: Decimal,
+}
+
+impl Supply {
+    /// Return the supply with `value` added, or `None` if either sum overflows
+    pub(crate) fn checked_add(self, value: Decimal) -> Option<Self> {
+        match value.is_sign_negative() {
+            true => self
+                .negative
+                .checked_add(value)
+                .map(|negative| Self { negative, ..self }),
+            false => self
+                .positive
+                .checked_add(value)
+                .map(|positive| Self { positive, ..self }),
+        }
+    }
+
+    /// Return the supply with `value`, which must previously have been added, taken out
+    pub(crate) fn remove(self, value: Decimal) -> Self {
+        match value.is_sign_negative() {
+            true => Self {
+                negative: self
+                    .negative
+                    .checked_sub(value)
+                    .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL)),
+                ..self
+            },
+            false => Self {
+                positive: self
+                    .positive
+                    .checked_sub(value)
+                    .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL)),
+                ..self
+            },
+        }
+    }
+
+    /// Return the total supply.  The sums have opposite signs, so adding them never overflows
+    pub(crate) fn total(self) -> Decimal {
+        self.positive
+            .checked_add(self.negative)
+            .unwrap_or_else(|| unreachable!("{}", msg::ERR_INTERNAL))
+    }
+}
+
+impl Default for Supply {
+    fn default() -> Self {
+        Self {
+            positive: Decimal::new(0, 0),
+            negative: Decimal::new(0, 0),
+        }
+    }
+}
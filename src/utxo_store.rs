@@ -0,0 +1,49 @@
+mod file_utxo_store;
+
+pub use file_utxo_store::FileUtxoStore;
+
+use crate::{tx::OutputTx, Result, Utxo, UtxoDelta, UtxoPool};
+
+/// Storage backend for the collection of UTXOs a `TxHandler` validates txs against and updates
+pub trait UtxoStore {
+    /// Return the tx output corresponding to `Utxo` or `None` if `Utxo` is not in the store
+    fn tx_output(&self, utxo: &Utxo) -> Option<&OutputTx>;
+
+    /// Return true if `Utxo` is in the store and false otherwise
+    fn contains(&self, utxo: &Utxo) -> bool {
+        self.tx_output(utxo).is_some()
+    }
+
+    /// Returns a `Vec` of all UTXOs in the store
+    fn all_utxos(&self) -> Vec<Utxo>;
+
+    /// Return the store's version, which changes whenever the store does
+    fn version(&self) -> u64;
+
+    /// Removes the `Utxo`s `delta` removes from the store, then adds the `Utxo`s it adds.  Fails,
+    /// leaving the store unchanged, if the changes cannot be recorded or `delta`'s preconditions
+    /// (see `UtxoPool::apply()`) do not hold.
+    fn apply_delta(&mut self, delta: &UtxoDelta) -> Result<()>;
+}
+
+impl UtxoStore for UtxoPool {
+    fn tx_output(&self, utxo: &Utxo) -> Option<&OutputTx> {
+        Self::tx_output(self, utxo)
+    }
+
+    fn contains(&self, utxo: &Utxo) -> bool {
+        Self::contains(self, utxo)
+    }
+
+    fn all_utxos(&self) -> Vec<Utxo> {
+        Self::all_utxos(self)
+    }
+
+    fn version(&self) -> u64 {
+        Self::version(self)
+    }
+
+    fn apply_delta(&mut self, delta: &UtxoDelta) -> Result<()> {
+        self.apply(delta).map(|_| ())
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{
+    byte_reader::ByteReader,
+    consts::{self, msg},
+    tx::{Address, OutputTx, PublicKey, TxHash, TxIdx},
+    Error, Result, Utxo, UtxoDelta, UtxoPool, UtxoStore,
+};
+use rust_decimal::Decimal;
+use sha2::{Digest, Sha256};
+use std::{
+    convert::TryFrom,
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    iter,
+    path::{Path, PathBuf},
+};
+
+/// Length of the checksum prefixed to each record (the leading bytes of the SHA-256 of its body)
+const CHECKSUM_LEN: usize = 8;
+const TAG_REMOVE: u8 = 0;
+const TAG_INSERT: u8 = 1;
+
+/// A `UtxoStore` persisted to a directory.  The full collection of UTXOs is also held in memory.
+///
+/// Each change is appended to a log as a checksummed record and fsynced before it is applied in
+/// memory.  Once the log holds `compaction_interval()` records it is folded into a snapshot: the
+/// snapshot is written to a temporary file and fsynced, renamed over the previous snapshot and
+/// the directory fsynced, and only then is the log truncated.  Wherever a crash occurs, replaying
+/// the log over the snapshot on `open()` recovers the last change `apply_delta()` reported as
+/// made; a record torn by a crash mid-write (cut short at the end of the log) is discarded.  Any
+/// other damage, such as a complete record failing its checksum, makes `open()` fail without
+/// touching either file.
+///
+/// If a failed append cannot be cut back off the log, the store is poisoned: it refuses further
+/// changes until `compact()` succeeds or the store is reopened, as later records would otherwise
+/// follow the partial one and be discarded on replay.
+#[derive(Debug)]
+pub struct FileUtxoStore {
+    /// In-memory copy of the persisted UTXOs
+    utxo_pool: UtxoPool,
+    dir: PathBuf,
+    log: File,
+    /// Length of the log up to the end of its last complete record
+    log_len: u64,
+    /// Number of records in the log
+    n_log_records: usize,
+    compaction_interval: usize,
+    /// Set when the log may end in a partial record
+    poisoned: bool,
+}
+
+/// One change recorded in the log or snapshot
+#[derive(Debug)]
+enum Change {
+    Remove(Utxo),
+    Insert(Utxo, OutputTx),
+}
+
+impl FileUtxoStore {
+    /// Opens the store persisted in `dir`, creating an empty one if `dir` holds none.  Fails if
+    /// either file is corrupt or a change in either file cannot be replayed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        // A temporary snapshot is left behind only by a compaction interrupted before its rename
+        let tmp_path = dir.join(consts::FILE_UTXO_STORE_SNAPSHOT_TMP_FILE_NAME);
+        if tmp_path.exists() {
+            fs::remove_file(&tmp_path)?;
+        }
+
+        let mut utxo_pool = UtxoPool::new();
+        let snapshot_path = dir.join(consts::FILE_UTXO_STORE_SNAPSHOT_FILE_NAME);
+        if snapshot_path.exists() {
+            let bytes = fs::read(&snapshot_path)?;
+            match read_records(&bytes) {
+                Some((records, complete_len)) if complete_len == bytes.len() => records
+                    .into_iter()
+                    .flatten()
+                    .try_for_each(|change| replay(&mut utxo_pool, change))
+                    .map_err(|_| Error::CorruptUtxoStore(snapshot_path))?,
+                _ => Err(Error::CorruptUtxoStore(snapshot_path))?,
+            }
+        }
+
+        let log_path = dir.join(consts::FILE_UTXO_STORE_LOG_FILE_NAME);
+        let mut log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&log_path)?;
+        let mut bytes = Vec::new();
+        log.read_to_end(&mut bytes)?;
+        let (records, complete_len) =
+            read_records(&bytes).ok_or_else(|| Error::CorruptUtxoStore(log_path.clone()))?;
+        let n_log_records = records.len();
+        records
+            .into_iter()
+            .flatten()
+            .try_for_each(|change| replay(&mut utxo_pool, change))
+            .map_err(|_| Error::CorruptUtxoStore(log_path))?;
+        let log_len =
+            u64::try_from(complete_len).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL));
+        if complete_len < bytes.len() {
+            log.set_len(log_len)?;
+            log.sync_all()?;
+        }
+        sync_dir(&dir)?;
+
+        Ok(Self {
+            utxo_pool,
+            dir,
+            log,
+            log_len,
+            n_log_records,
+            compaction_interval: consts::FILE_UTXO_STORE_COMPACTION_INTERVAL,
+            poisoned: false,
+        })
+    }
+
+    /// Return the in-memory copy of the persisted UTXOs, for queries beyond those of `UtxoStore`
+    #[must_use]
+    pub const fn utxo_pool(&self) -> &UtxoPool {
+        &self.utxo_pool
+    }
+
+    /// Return the directory the store is persisted in
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Return the number of log records after which the log is folded into the snapshot
+    #[must_use]
+    pub const fn compaction_interval(&self) -> usize {
+        self.compaction_interval
+    }
+
+    pub const fn set_compaction_interval(&mut self, compaction_interval: usize) -> &mut Self {
+        self.compaction_interval = compaction_interval;
+        self
+    }
+
+    /// Return true if a failed append could not be cut back off the log, in which case the store
+    /// refuses changes until `compact()` succeeds
+    #[must_use]
+    pub const fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Folds the log into the snapshot, leaving the log empty.  Emptying the log also discards any
+    /// partial record left by a failed append, so a successful compaction clears poisoning.
+    pub fn compact(&mut self) -> Result<&mut Self> {
+        let utxos = self.utxo_pool.all_utxos();
+        let record = encode_record(
+            iter::empty(),
+            utxos
+                .iter()
+                .filter_map(|utxo| self.utxo_pool.tx_output(utxo).map(|tx_out| (utxo, tx_out))),
+        );
+
+        let tmp_path = self
+            .dir
+            .join(consts::FILE_UTXO_STORE_SNAPSHOT_TMP_FILE_NAME);
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(&record)?;
+        tmp.sync_all()?;
+        fs::rename(
+            &tmp_path,
+            self.dir.join(consts::FILE_UTXO_STORE_SNAPSHOT_FILE_NAME),
+        )?;
+        sync_dir(&self.dir)?;
+
+        // The snapshot is durable and includes every logged change, so the log can be emptied
+        self.log.set_len(0)?;
+        self.log.sync_all()?;
+        self.log_len = 0;
+        self.n_log_records = 0;
+        self.poisoned = false;
+        Ok(self)
+    }
+}
+
+impl UtxoStore for FileUtxoStore {
+    fn tx_output(&self, utxo: &Utxo) -> Option<&OutputTx> {
+        self.utxo_pool.tx_output(utxo)
+    }
+
+    fn contains(&self, utxo: &Utxo) -> bool {
+        self.utxo_pool.contains(utxo)
+    }
+
+    fn all_utxos(&self) -> Vec<Utxo> {
+        self.utxo_pool.all_utxos()
+    }
+
+    fn version(&self) -> u64 {
+        self.utxo_pool.version()
+    }
+
+    fn apply_delta(&mut self, delta: &UtxoDelta) -> Result<()> {
+        if self.poisoned {
+            Err(Error::UtxoStorePoisoned(self.dir.clone()))?;
+        }
+        self.utxo_pool.check_delta(delta)?;
+        if delta.is_empty() {
+            return Ok(());
+        }
+        // Compact ahead of the append so a failed compaction never follows a change being made
+        if self.n_log_records >= self.compaction_interval {
+            self.compact()?;
+        }
+
+        let record = encode_record(
+            delta.removed().iter().map(|(utxo, _)| utxo),
+            delta.added().iter().map(|(utxo, tx_out)| (utxo, tx_out)),
+        );
+        if let Err(err) = self
+            .log
+            .write_all(&record)
+            .and_then(|()| self.log.sync_data())
+        {
+            // Drop any partial record so later appends follow the last complete one
+            self.poisoned = self.log.set_len(self.log_len).is_err();
+            Err(err)?;
+        }
+        self.log_len = self.log_len.saturating_add(
+            u64::try_from(record.len()).unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL)),
+        );
+        self.n_log_records = self.n_log_records.saturating_add(1);
+        self.utxo_pool.apply_delta(delta);
+        Ok(())
+    }
+}
+
+/// Applies `change` to `utxo_pool`.  Replaying a log over a snapshot which already includes it
+/// yields the same pool, as each change sets or clears a `Utxo` outright.  Fails if the change
+/// would overflow the pool's total supply, which no change the store made can do.
+fn replay(utxo_pool: &mut UtxoPool, change: Change) -> Result<()> {
+    match change {
+        Change::Remove(utxo) => {
+            utxo_pool.remove_utxo(&utxo);
+        }
+        Change::Insert(utxo, tx_out) => {
+            utxo_pool.insert_or_replace(utxo, tx_out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Encodes a record removing `removed` then inserting `inserted` as
+/// `body length (u64 LE) | checksum | body`
+fn encode_record<'a>(
+    removed: impl Iterator<Item = &'a Utxo>,
+    inserted: impl Iterator<Item = (&'a Utxo, &'a OutputTx)>,
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    for utxo in removed {
+        body.push(TAG_REMOVE);
+        encode_utxo(&mut body, utxo);
+    }
+    for (utxo, tx_out) in inserted {
+        body.push(TAG_INSERT);
+        encode_utxo(&mut body, utxo);
+        body.extend_from_slice(&tx_out.value().serialize());
+        body.extend_from_slice(tx_out.address().as_bytes());
+    }
+
+    let mut record = u64::try_from(body.len())
+        .unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL))
+        .to_le_bytes()
+        .to_vec();
+    record.extend(Sha256::digest(&body).iter().take(CHECKSUM_LEN));
+    record.extend(body);
+    record
+}
+
+fn encode_utxo(bytes: &mut Vec<u8>, utxo: &Utxo) {
+    bytes.extend_from_slice(
+        &u64::try_from(utxo.tx_hash().as_ref().len())
+            .unwrap_or_else(|_| unreachable!("{}", msg::ERR_INTERNAL))
+            .to_le_bytes(),
+    );
+    bytes.extend_from_slice(utxo.tx_hash().as_ref());
+    bytes.extend_from_slice(&utxo.tx_idx().0.to_le_bytes());
+}
+
+/// Decodes the complete records at the start of `bytes`, returning them and the number of bytes
+/// they span.  Any bytes after them are a record cut short by a crash mid-append.  Returns `None`
+/// if a complete record fails its checksum or cannot be decoded.
+fn read_records(bytes: &[u8]) -> Option<(Vec<Vec<Change>>, usize)> {
+    let mut reader = ByteReader::new(bytes);
+    let mut records = Vec::new();
+    let mut complete_len = 0;
+    while let Some((checksum, body)) = read_record(&mut reader) {
+        records.push(decode_record(checksum, body)?);
+        complete_len = bytes.len().saturating_sub(reader.len());
+    }
+    Some((records, complete_len))
+}
+
+/// Return the checksum and body of the next record, or `None` if `reader` ends partway through it
+fn read_record<'a>(reader: &mut ByteReader<'a>) -> Option<(&'a [u8], &'a [u8])> {
+    let body_len = usize::try_from(reader.u64()?).ok()?;
+    let checksum = reader.take(CHECKSUM_LEN)?;
+    let body = reader.take(body_len)?;
+    Some((checksum, body))
+}
+
+fn decode_record(checksum: &[u8], body: &[u8]) -> Option<Vec<Change>> {
+    if Sha256::digest(body).get(..CHECKSUM_LEN) != Some(checksum) {
+        return None;
+    }
+
+    let mut body = ByteReader::new(body);
+    let mut changes = Vec::new();
+    while !body.is_empty() {
+        changes.push(read_change(&mut body)?);
+    }
+    Some(changes)
+}
+
+fn read_change(reader: &mut ByteReader<'_>) -> Option<Change> {
+    match reader.u8()? {
+        TAG_REMOVE => Some(Change::Remove(read_utxo(reader)?)),
+        TAG_INSERT => Some(Change::Insert(read_utxo(reader)?, read_tx_out(reader)?)),
+        _ => None,
+    }
+}
+
+fn read_utxo(reader: &mut ByteReader<'_>) -> Option<Utxo> {
+    let tx_hash = TxHash::try_from(reader.len_prefixed()?).ok()?;
+    let tx_idx = TxIdx(reader.u64()?);
+    Some(Utxo::new(tx_hash, tx_idx))
+}
+
+fn read_tx_out(reader: &mut ByteReader<'_>) -> Option<OutputTx> {
+    let value = reader
+        .take(16)
+        .and_then(|bytes| <[u8; 16]>::try_from(bytes).ok())
+        .map(Decimal::deserialize)?;
+    let address = PublicKey::from_slice(reader.take(ed25519_dalek::PUBLIC_KEY_LENGTH)?).ok()?;
+    Some(OutputTx::new(value, address))
+}
+
+/// Makes file creations and renames in `dir` durable
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/// Directories cannot be opened for syncing on this platform
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
+}